@@ -0,0 +1,103 @@
+use gtk::{gdk, gio, glib, prelude::*};
+
+use crate::jobs::JobKind;
+
+const URI_LIST: &str = "text/uri-list";
+/// Also understood by other file managers, and the only way they tell copied and cut files apart.
+const GNOME_COPIED_FILES: &str = "x-special/gnome-copied-files";
+
+pub fn set_files(
+    clipboard: &gdk::Clipboard,
+    files: &[gio::File],
+    kind: JobKind,
+) -> Result<(), String> {
+    let uris: Vec<String> = files.iter().map(|file| file.uri().into()).collect();
+    let uri_list: String = uris.iter().map(|uri| format!("{}\r\n", uri)).collect();
+    let copied_files = format!(
+        "{}\n{}",
+        match kind {
+            JobKind::Copy => "copy",
+            JobKind::Move => "cut",
+        },
+        uris.join("\n")
+    );
+    let content = gdk::ContentProvider::new_union(&[
+        gdk::ContentProvider::for_bytes(
+            GNOME_COPIED_FILES,
+            &glib::Bytes::from_owned(copied_files.into_bytes()),
+        ),
+        gdk::ContentProvider::for_bytes(URI_LIST, &glib::Bytes::from_owned(uri_list.into_bytes())),
+    ]);
+    clipboard
+        .set_content(Some(&content))
+        .map_err(|error| error.to_string())
+}
+
+fn parse(contents: &str, mime_type: &str) -> (Vec<gio::File>, JobKind) {
+    let mut lines = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty());
+    let kind = if mime_type == GNOME_COPIED_FILES && lines.next() == Some("cut") {
+        JobKind::Move
+    } else {
+        JobKind::Copy
+    };
+    let files = lines
+        .filter(|line| !line.starts_with('#'))
+        .map(gio::File::for_uri)
+        .collect();
+    (files, kind)
+}
+
+/// Reads files copied or cut here or in another file manager. `callback` isn't called when the
+/// clipboard doesn't hold any files.
+pub fn read_files(
+    clipboard: &gdk::Clipboard,
+    callback: impl FnOnce(Vec<gio::File>, JobKind) + 'static,
+) {
+    let clipboard = clipboard.clone();
+    glib::MainContext::default().spawn_local(async move {
+        let (stream, mime_type) = match clipboard
+            .read_future(&[GNOME_COPIED_FILES, URI_LIST], glib::PRIORITY_DEFAULT)
+            .await
+        {
+            Ok(read) => read,
+            Err(_) => return,
+        };
+        let output = gio::MemoryOutputStream::new_resizable();
+        let spliced = output
+            .splice_future(
+                &stream,
+                gio::OutputStreamSpliceFlags::CLOSE_SOURCE
+                    | gio::OutputStreamSpliceFlags::CLOSE_TARGET,
+                glib::PRIORITY_DEFAULT,
+            )
+            .await;
+        if spliced.is_err() {
+            return;
+        }
+        let bytes = output.steal_as_bytes();
+        let (files, kind) = parse(&String::from_utf8_lossy(&bytes), &mime_type);
+        if !files.is_empty() {
+            callback(files, kind);
+        }
+    });
+}
+
+/// Empties the clipboard once the files cut to it were moved, unless something else was copied
+/// or cut since.
+pub fn clear_cut(clipboard: &gdk::Clipboard, moved: Vec<gio::File>) {
+    let target = clipboard.clone();
+    read_files(clipboard, move |files, kind| {
+        let unchanged = kind == JobKind::Move
+            && files.len() == moved.len()
+            && files
+                .iter()
+                .zip(&moved)
+                .all(|(file, moved)| file.equal(moved));
+        if unchanged {
+            let _ = target.set_content(gdk::ContentProvider::NONE);
+        }
+    });
+}
@@ -1,14 +1,20 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::ffi::OsString;
 use std::fmt;
 use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use std::time::Duration;
 
 use bytesize::ByteSize;
-use gtk::gio::{File, FileInfo, FileType};
+use gtk::gio::{self, File, FileInfo, FileType};
 use gtk::glib;
 use gtk::prelude::*;
 
 use gtk_list_provider::{ListColumn, ListContent, ListProvider};
 
+use crate::filter::{self, FileFilter};
+
 struct NameColumn;
 
 impl NameColumn {
@@ -128,6 +134,42 @@ impl ListColumn for DirectoryColumn {
             DirectoryColumn::Modified => ModifiedColumn::sort(a, b),
         }
     }
+    fn context_menu(&self, item: &Self::ModelItem) -> Option<gio::MenuModel> {
+        let menu = gio::Menu::new();
+        if let Some(path) = directory_path(item) {
+            let open_in_new_tab = gio::MenuItem::new(Some("Open in New Tab"), None);
+            open_in_new_tab
+                .set_action_and_target_value(Some("win.open-in-new-tab"), Some(&folder_uri(&path)));
+            menu.append_item(&open_in_new_tab);
+        }
+        menu.append(Some("Copy"), Some("win.copy"));
+        menu.append(Some("Cut"), Some("win.cut"));
+        menu.append(Some("Paste"), Some("win.paste"));
+        menu.append(Some("Rename…"), Some("win.rename"));
+        menu.append(Some("Move to Trash"), Some("win.trash"));
+        menu.append(Some("Delete Permanently…"), Some("win.delete"));
+        Some(menu.upcast())
+    }
+    fn middle_click(&self, item: &Self::ModelItem, widget: &gtk::Widget) {
+        if let Some(path) = directory_path(item) {
+            let _ = widget.activate_action("win.open-in-new-tab", Some(&folder_uri(&path)));
+        }
+    }
+}
+
+/// The target for `win.open-in-new-tab`. A URI keeps paths that aren't UTF-8 intact.
+fn folder_uri(path: &Path) -> glib::Variant {
+    File::for_path(path).uri().to_variant()
+}
+
+/// The path of a listed folder, or `None` for other files.
+fn directory_path(item: &FileInfo) -> Option<PathBuf> {
+    if item.file_type() != FileType::Directory {
+        return None;
+    }
+    item.attribute_object("standard::file")
+        .and_downcast::<File>()
+        .and_then(|file| file.path())
 }
 
 pub struct FileDetail;
@@ -172,32 +214,83 @@ impl ListContent for FileDetail {
 #[derive(Clone)]
 pub struct DirectoryProvider {
     pub(crate) directory: gtk::DirectoryList,
+    filtered: gtk::FilterListModel,
+    filter: gtk::CustomFilter,
+    settings: Rc<RefCell<FileFilter>>,
+    hidden_names: Rc<RefCell<HashSet<OsString>>>,
 }
 
 impl DirectoryProvider {
     pub fn new(path: &Path) -> Self {
+        let directory = gtk::DirectoryList::new(
+            Some("standard::name,standard::icon,standard::size,standard::is-hidden,time::modified"),
+            Some(&File::for_path(path)),
+        );
+        let settings = Rc::new(RefCell::new(FileFilter::default()));
+        let hidden_names = Rc::new(RefCell::new(filter::read_hidden_names(path)));
+        let filter = gtk::CustomFilter::new(
+            glib::clone!(@strong settings, @strong hidden_names => move |item| {
+                let item = item.downcast_ref::<FileInfo>().unwrap();
+                settings.borrow().matches(item, &hidden_names.borrow())
+            }),
+        );
+        let filtered = gtk::FilterListModel::new(Some(directory.clone()), Some(filter.clone()));
+        // Connected after the filter model, so it has caught up before being refiltered
+        directory.connect_items_changed(
+            glib::clone!(@strong hidden_names, @weak filter => move |directory, position, removed, added| {
+                // Removed items can't be looked at any more, so any of them might be `.hidden`
+                let hidden_changed = removed > 0
+                    || (position..position + added).any(|position| {
+                        directory
+                            .item(position)
+                            .and_downcast::<FileInfo>()
+                            .map_or(false, |info| info.name().as_os_str() == ".hidden")
+                    });
+                if !hidden_changed {
+                    return;
+                }
+                let names = directory
+                    .file()
+                    .and_then(|file| file.path())
+                    .map(|path| filter::read_hidden_names(&path))
+                    .unwrap_or_default();
+                if names != *hidden_names.borrow() {
+                    hidden_names.replace(names);
+                    filter.changed(gtk::FilterChange::Different);
+                }
+            }),
+        );
         DirectoryProvider {
-            directory: gtk::DirectoryList::new(
-                Some("standard::name,standard::icon,standard::size,time::modified"),
-                Some(&File::for_path(path)),
-            ),
+            filtered,
+            directory,
+            filter,
+            settings,
+            hidden_names,
         }
     }
     pub fn path(&self) -> PathBuf {
         self.directory.file().unwrap().path().unwrap()
     }
     pub fn set_path(&self, path: &Path) {
+        self.hidden_names.replace(filter::read_hidden_names(path));
         self.directory.set_file(Some(&File::for_path(path)))
     }
+    pub fn update_filter(&self, update: impl FnOnce(&mut FileFilter)) {
+        update(&mut self.settings.borrow_mut());
+        self.filter.changed(gtk::FilterChange::Different);
+    }
+    pub fn set_show_hidden(&self, show_hidden: bool) {
+        self.update_filter(|filter| filter.show_hidden = show_hidden);
+    }
 }
 
 impl ListProvider for DirectoryProvider {
-    type Model = gtk::DirectoryList;
+    type Model = gtk::FilterListModel;
     type ModelItem = FileInfo;
     type Column = DirectoryColumn;
     type Detail = FileDetail;
     fn model(&self) -> Self::Model {
-        self.directory.clone()
+        self.filtered.clone()
     }
     fn columns(&self) -> Vec<Self::Column> {
         vec![
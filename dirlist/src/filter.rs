@@ -0,0 +1,330 @@
+use std::{collections::HashSet, ffi::OsString, path::Path};
+
+use bytesize::ByteSize;
+use gtk::{
+    gio::{FileInfo, FileType},
+    glib,
+    prelude::*,
+};
+
+use crate::directory::DirectoryProvider;
+
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum FileKind {
+    #[default]
+    Any,
+    Files,
+    Folders,
+}
+
+/// Which files a folder view lists. Everything is listed by default, except hidden files.
+#[derive(Default)]
+pub struct FileFilter {
+    pub show_hidden: bool,
+    /// Shell-style pattern the name has to match, ignoring case.
+    pub pattern: Option<String>,
+    pub kind: FileKind,
+    /// Size bounds in bytes, inclusive. Folders aren't limited by these.
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    pub modified_after: Option<glib::DateTime>,
+    pub modified_before: Option<glib::DateTime>,
+}
+
+impl FileFilter {
+    /// `hidden_names` are the names listed in the folder's `.hidden` file.
+    pub fn matches(&self, item: &FileInfo, hidden_names: &HashSet<OsString>) -> bool {
+        let name = item.name();
+        if !self.show_hidden && (item.is_hidden() || hidden_names.contains(name.as_os_str())) {
+            return false;
+        }
+        let is_folder = item.file_type() == FileType::Directory;
+        let kind_matches = match self.kind {
+            FileKind::Any => true,
+            FileKind::Files => !is_folder,
+            FileKind::Folders => is_folder,
+        };
+        if !kind_matches {
+            return false;
+        }
+        if let Some(pattern) = &self.pattern {
+            if !glob_matches(pattern, &name.to_string_lossy()) {
+                return false;
+            }
+        }
+        if !is_folder {
+            let size = item.size() as u64;
+            if self.min_size.map_or(false, |min| size < min)
+                || self.max_size.map_or(false, |max| size > max)
+            {
+                return false;
+            }
+        }
+        let modified = item.modification_date_time();
+        if let Some(after) = &self.modified_after {
+            if modified.as_ref().map_or(true, |modified| modified < after) {
+                return false;
+            }
+        }
+        if let Some(before) = &self.modified_before {
+            if modified
+                .as_ref()
+                .map_or(true, |modified| modified >= before)
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Matches `name` against `pattern`, where `*` is any run of characters and `?` is any one
+/// character. Case is ignored.
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Where to resume after the last `*` if the rest doesn't match, letting it take one more
+    // character
+    let mut backtrack = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            p += 1;
+            backtrack = Some((p, n));
+        } else if let Some((star_p, star_n)) = backtrack {
+            p = star_p;
+            n = star_n + 1;
+            backtrack = Some((star_p, n));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Names listed in `directory/.hidden`, one per line, which are hidden like dotfiles.
+pub fn read_hidden_names(directory: &Path) -> HashSet<OsString> {
+    std::fs::read_to_string(directory.join(".hidden"))
+        .map(|contents| {
+            contents
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(OsString::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn parse_pattern(text: &str) -> Result<Option<String>, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        Ok(None)
+    } else if text.contains(['*', '?']) {
+        Ok(Some(text.to_owned()))
+    } else {
+        // Without wildcards, match the text anywhere in the name
+        Ok(Some(format!("*{}*", text)))
+    }
+}
+
+fn parse_size(text: &str) -> Result<Option<u64>, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    text.parse::<ByteSize>()
+        .map(|size| Some(size.as_u64()))
+        .map_err(|_| String::from("Sizes are written like 500, 10 KB or 1.5 GiB"))
+}
+
+/// Parses a local date written as YYYY-MM-DD, as midnight at its start.
+fn parse_date(text: &str) -> Result<Option<glib::DateTime>, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    let invalid = || String::from("Dates are written as YYYY-MM-DD");
+    let mut parts = text.splitn(3, '-').map(|part| part.parse::<i32>());
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(Ok(year)), Some(Ok(month)), Some(Ok(day))) => {
+            glib::DateTime::from_local(year, month, day, 0, 0, 0.0)
+                .map(Some)
+                .map_err(|_| invalid())
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// Updates the filter whenever `entry` changes. Text that doesn't parse marks the entry and is
+/// treated as empty.
+fn connect_entry<T: 'static>(
+    entry: &gtk::Entry,
+    provider: &DirectoryProvider,
+    parse: fn(&str) -> Result<Option<T>, String>,
+    apply: fn(&mut FileFilter, Option<T>),
+) {
+    entry.connect_changed(glib::clone!(@strong provider => move |entry| {
+        let value = match parse(&entry.text()) {
+            Ok(value) => {
+                entry.remove_css_class("error");
+                entry.set_tooltip_text(None);
+                value
+            }
+            Err(error) => {
+                entry.add_css_class("error");
+                entry.set_tooltip_text(Some(&error));
+                None
+            }
+        };
+        provider.update_filter(|filter| apply(filter, value));
+    }));
+}
+
+/// Fields for narrowing down the files `provider` lists. Hiding the bar clears them.
+pub fn build_filter_bar(provider: &DirectoryProvider) -> gtk::Revealer {
+    const SPACING: i32 = 6;
+    let name = gtk::Entry::builder()
+        .hexpand(true)
+        .placeholder_text("Name, e.g. *.txt")
+        .build();
+    let kind = gtk::DropDown::from_strings(&["Files and Folders", "Files Only", "Folders Only"]);
+    let min_size = gtk::Entry::builder()
+        .width_chars(8)
+        .placeholder_text("Min size")
+        .build();
+    let max_size = gtk::Entry::builder()
+        .width_chars(8)
+        .placeholder_text("Max size")
+        .build();
+    let modified_after = gtk::Entry::builder()
+        .width_chars(15)
+        .placeholder_text("From YYYY-MM-DD")
+        .build();
+    let modified_before = gtk::Entry::builder()
+        .width_chars(15)
+        .placeholder_text("To YYYY-MM-DD")
+        .build();
+
+    let bar = gtk::Box::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .spacing(SPACING)
+        .margin_start(SPACING)
+        .margin_end(SPACING)
+        .build();
+    bar.append(&name);
+    bar.append(&kind);
+    bar.append(&min_size);
+    bar.append(&max_size);
+    bar.append(&modified_after);
+    bar.append(&modified_before);
+    let revealer = gtk::Revealer::builder().child(&bar).build();
+
+    connect_entry(&name, provider, parse_pattern, |filter, pattern| {
+        filter.pattern = pattern;
+    });
+    kind.connect_selected_notify(glib::clone!(@strong provider => move |kind| {
+        let kind = match kind.selected() {
+            1 => FileKind::Files,
+            2 => FileKind::Folders,
+            _ => FileKind::Any,
+        };
+        provider.update_filter(|filter| filter.kind = kind);
+    }));
+    connect_entry(&min_size, provider, parse_size, |filter, size| {
+        filter.min_size = size;
+    });
+    connect_entry(&max_size, provider, parse_size, |filter, size| {
+        filter.max_size = size;
+    });
+    connect_entry(&modified_after, provider, parse_date, |filter, date| {
+        filter.modified_after = date;
+    });
+    connect_entry(&modified_before, provider, parse_date, |filter, date| {
+        // Up to the end of that day
+        filter.modified_before = date.and_then(|date| date.add_days(1).ok());
+    });
+
+    revealer.connect_reveal_child_notify(glib::clone!(@weak name, @weak kind, @weak min_size, @weak max_size, @weak modified_after, @weak modified_before => move |revealer| {
+        if revealer.reveals_child() {
+            name.grab_focus();
+        } else {
+            for entry in [&name, &min_size, &max_size, &modified_after, &modified_before] {
+                entry.set_text("");
+            }
+            kind.set_selected(0);
+        }
+    }));
+    revealer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_wildcards() {
+        assert!(glob_matches("*.txt", "notes.txt"));
+        assert!(glob_matches("*.TXT", "Notes.txt"));
+        assert!(glob_matches("?otes.txt", "notes.txt"));
+        assert!(!glob_matches("*.txt", "notes.txt.bak"));
+        assert!(!glob_matches("?otes.txt", "otes.txt"));
+    }
+
+    #[test]
+    fn glob_backtracking() {
+        // The first "b" isn't the one the pattern needs, so `*` has to take more
+        assert!(glob_matches("a*bc", "abxbc"));
+        assert!(glob_matches("*a*b*", "xxaxxbxx"));
+        assert!(glob_matches("*ab", "aab"));
+        assert!(!glob_matches("a*bc", "abxbd"));
+    }
+
+    #[test]
+    fn glob_empty() {
+        assert!(glob_matches("", ""));
+        assert!(glob_matches("*", ""));
+        assert!(glob_matches("**", "anything"));
+        assert!(!glob_matches("", "name"));
+        assert!(!glob_matches("?", ""));
+    }
+
+    #[test]
+    fn patterns() {
+        assert_eq!(parse_pattern(""), Ok(None));
+        assert_eq!(parse_pattern("  "), Ok(None));
+        assert_eq!(parse_pattern("*.rs"), Ok(Some(String::from("*.rs"))));
+        assert_eq!(parse_pattern(" notes "), Ok(Some(String::from("*notes*"))));
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size(""), Ok(None));
+        assert_eq!(parse_size("500"), Ok(Some(500)));
+        assert_eq!(parse_size("10 KB"), Ok(Some(10_000)));
+        assert_eq!(parse_size("1 KiB"), Ok(Some(1024)));
+        assert!(parse_size("large").is_err());
+    }
+
+    #[test]
+    fn dates() {
+        assert!(parse_date("").unwrap().is_none());
+        let date = parse_date("2024-02-29").unwrap().unwrap();
+        assert_eq!(
+            (date.year(), date.month(), date.day_of_month()),
+            (2024, 2, 29)
+        );
+        assert_eq!((date.hour(), date.minute()), (0, 0));
+    }
+
+    #[test]
+    fn invalid_dates() {
+        assert!(parse_date("2023-02-29").is_err());
+        assert!(parse_date("2024-13-01").is_err());
+        assert!(parse_date("2024-01").is_err());
+        assert!(parse_date("yesterday").is_err());
+        assert!(parse_date("2024-01-01-01").is_err());
+    }
+}
@@ -0,0 +1,521 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    path::Path,
+    rc::{Rc, Weak},
+};
+
+use gtk::{gio, glib, prelude::*};
+
+use crate::operations::show_error;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JobKind {
+    Copy,
+    Move,
+}
+
+enum Step {
+    /// A file the user chose, checked for conflicts before anything is done with it.
+    Item {
+        source: gio::File,
+        target: gio::File,
+    },
+    /// Decides how to handle `source` once it's known whether it's a folder.
+    Expand {
+        source: gio::File,
+        target: gio::File,
+        overwrite: bool,
+    },
+    Transfer {
+        source: gio::File,
+        target: gio::File,
+        overwrite: bool,
+    },
+    MakeDirectory {
+        target: gio::File,
+        merge: bool,
+    },
+    /// Removes a folder after everything in it was moved.
+    RemoveDirectory {
+        source: gio::File,
+    },
+}
+
+struct Job {
+    kind: JobKind,
+    steps: RefCell<VecDeque<Step>>,
+    cancellable: gio::Cancellable,
+    on_finished: RefCell<Option<Box<dyn FnOnce()>>>,
+    /// Whether the user chose to skip a file that was in the way.
+    skipped: Cell<bool>,
+    row: gtk::Box,
+    label: gtk::Label,
+    progress: gtk::ProgressBar,
+}
+
+fn display_name(file: &gio::File) -> String {
+    file.basename()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| file.uri().into())
+}
+
+async fn file_type(file: &gio::File) -> Option<gio::FileType> {
+    file.query_info_future(
+        "standard::type",
+        gio::FileQueryInfoFlags::NOFOLLOW_SYMLINKS,
+        glib::PRIORITY_DEFAULT,
+    )
+    .await
+    .ok()
+    .map(|info| info.file_type())
+}
+
+/// A sibling of `target` that doesn't exist yet, such as "notes (2).txt".
+async fn unique_target(target: &gio::File) -> gio::File {
+    let path = target.path().unwrap_or_default();
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+    let parent = path.parent().map(Path::to_owned).unwrap_or_default();
+    let mut n = 2;
+    loop {
+        let file = gio::File::for_path(parent.join(format!("{} ({}){}", stem, n, extension)));
+        if file_type(&file).await.is_none() {
+            return file;
+        }
+        n += 1;
+    }
+}
+
+/// Steps for everything in the folder `source`, going into `target`.
+async fn children(
+    source: &gio::File,
+    target: &gio::File,
+    overwrite: bool,
+) -> Result<Vec<Step>, glib::Error> {
+    const BATCH_SIZE: i32 = 100;
+    let enumerator = source
+        .enumerate_children_future(
+            "standard::name",
+            gio::FileQueryInfoFlags::NOFOLLOW_SYMLINKS,
+            glib::PRIORITY_DEFAULT,
+        )
+        .await?;
+    let mut steps = Vec::new();
+    loop {
+        let infos = enumerator
+            .next_files_future(BATCH_SIZE, glib::PRIORITY_DEFAULT)
+            .await?;
+        if infos.is_empty() {
+            return Ok(steps);
+        }
+        steps.extend(infos.into_iter().map(|info| Step::Expand {
+            source: source.child(info.name()),
+            target: target.child(info.name()),
+            overwrite,
+        }));
+    }
+}
+
+/// Runs a step's file system calls without blocking the window.
+fn spawn(future: impl std::future::Future<Output = ()> + 'static) {
+    glib::MainContext::default().spawn_local(future);
+}
+
+/// Runs copy and move jobs one at a time, listing them with their progress in a popover.
+#[derive(Clone)]
+pub struct JobQueue {
+    window: glib::WeakRef<gtk::Window>,
+    jobs: Rc<RefCell<VecDeque<Rc<Job>>>>,
+    list: gtk::Box,
+    pub button: gtk::MenuButton,
+}
+
+/// Held by the widgets of a job's row, which the queue itself holds.
+pub struct WeakJobQueue {
+    window: glib::WeakRef<gtk::Window>,
+    jobs: Weak<RefCell<VecDeque<Rc<Job>>>>,
+    list: glib::WeakRef<gtk::Box>,
+    button: glib::WeakRef<gtk::MenuButton>,
+}
+
+impl glib::clone::Downgrade for JobQueue {
+    type Weak = WeakJobQueue;
+    fn downgrade(&self) -> WeakJobQueue {
+        WeakJobQueue {
+            window: self.window.clone(),
+            jobs: Rc::downgrade(&self.jobs),
+            list: self.list.downgrade(),
+            button: self.button.downgrade(),
+        }
+    }
+}
+
+impl glib::clone::Upgrade for WeakJobQueue {
+    type Strong = JobQueue;
+    fn upgrade(&self) -> Option<JobQueue> {
+        Some(JobQueue {
+            window: self.window.clone(),
+            jobs: self.jobs.upgrade()?,
+            list: self.list.upgrade()?,
+            button: self.button.upgrade()?,
+        })
+    }
+}
+
+impl JobQueue {
+    pub fn new(window: &impl IsA<gtk::Window>) -> Self {
+        let list = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(6)
+            .width_request(280)
+            .build();
+        let popover = gtk::Popover::builder().child(&list).build();
+        let button = gtk::MenuButton::builder()
+            .icon_name("document-save-symbolic")
+            .tooltip_text("File Operations")
+            .popover(&popover)
+            .visible(false)
+            .build();
+        JobQueue {
+            window: window.upcast_ref::<gtk::Window>().downgrade(),
+            jobs: Rc::default(),
+            list,
+            button,
+        }
+    }
+
+    /// Queues copying or moving `sources` into the folder `destination`. `on_finished` is called
+    /// if the job completes without being cancelled, failing or skipping any files.
+    pub fn add(
+        &self,
+        kind: JobKind,
+        sources: Vec<gio::File>,
+        destination: &gio::File,
+        on_finished: Option<Box<dyn FnOnce()>>,
+    ) {
+        let steps = sources
+            .into_iter()
+            .filter_map(|source| {
+                let target = destination.child(source.basename()?);
+                Some(Step::Item { source, target })
+            })
+            .collect();
+        let label = gtk::Label::builder()
+            .label(match kind {
+                JobKind::Copy => "Waiting to copy",
+                JobKind::Move => "Waiting to move",
+            })
+            .xalign(0.0)
+            .ellipsize(gtk::pango::EllipsizeMode::Middle)
+            .build();
+        let progress = gtk::ProgressBar::new();
+        let text = gtk::Box::new(gtk::Orientation::Vertical, 2);
+        text.append(&label);
+        text.append(&progress);
+        text.set_hexpand(true);
+        let cancel = gtk::Button::builder()
+            .icon_name("process-stop-symbolic")
+            .tooltip_text("Cancel")
+            .css_classes(["flat"])
+            .valign(gtk::Align::Center)
+            .build();
+        let row = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+        row.append(&text);
+        row.append(&cancel);
+        self.list.append(&row);
+
+        let job = Rc::new(Job {
+            kind,
+            steps: RefCell::new(steps),
+            cancellable: gio::Cancellable::new(),
+            on_finished: RefCell::new(on_finished),
+            skipped: Cell::new(false),
+            row,
+            label,
+            progress,
+        });
+        cancel.connect_clicked(glib::clone!(@weak self as queue, @weak job => move |_| {
+            queue.cancel(&job);
+        }));
+        self.jobs.borrow_mut().push_back(job.clone());
+        self.button.set_visible(true);
+        if self.jobs.borrow().len() == 1 {
+            self.run(&job);
+        }
+    }
+
+    fn cancel(&self, job: &Rc<Job>) {
+        job.cancellable.cancel();
+        // A running job notices when its current step ends, queued ones can go right away
+        let running = self
+            .jobs
+            .borrow()
+            .front()
+            .map_or(false, |front| Rc::ptr_eq(front, job));
+        if !running {
+            self.jobs.borrow_mut().retain(|j| !Rc::ptr_eq(j, job));
+            self.list.remove(&job.row);
+        }
+    }
+
+    fn finish(&self, job: &Rc<Job>, succeeded: bool) {
+        self.list.remove(&job.row);
+        self.jobs.borrow_mut().retain(|j| !Rc::ptr_eq(j, job));
+        if succeeded && !job.skipped.get() {
+            if let Some(on_finished) = job.on_finished.take() {
+                on_finished();
+            }
+        }
+        let next = self.jobs.borrow().front().cloned();
+        match next {
+            Some(next) => self.run(&next),
+            None => {
+                self.button.popdown();
+                self.button.set_visible(false);
+            }
+        }
+    }
+
+    fn fail(&self, job: &Rc<Job>, file: &gio::File, error: &glib::Error) {
+        if !error.matches(gio::IOErrorEnum::Cancelled) {
+            if let Some(window) = self.window.upgrade() {
+                let action = match job.kind {
+                    JobKind::Copy => "copying",
+                    JobKind::Move => "moving",
+                };
+                show_error(
+                    &window,
+                    &format!("Error {} {}", action, display_name(file)),
+                    &error.to_string(),
+                );
+            }
+        }
+        self.finish(job, false);
+    }
+
+    /// Runs the job's next step, which calls this again once it's done.
+    fn run(&self, job: &Rc<Job>) {
+        if job.cancellable.is_cancelled() {
+            self.finish(job, false);
+            return;
+        }
+        let step = job.steps.borrow_mut().pop_front();
+        let queue = self.clone();
+        let job = job.clone();
+        match step {
+            None => self.finish(&job, true),
+            Some(Step::Item { source, target }) => {
+                spawn(async move { queue.check_conflict(&job, source, target).await })
+            }
+            // Moves are tried whole first, as that's a rename on the same file system
+            Some(Step::Expand {
+                source,
+                target,
+                overwrite,
+            }) => spawn(async move {
+                let is_directory = job.kind == JobKind::Copy
+                    && file_type(&source).await == Some(gio::FileType::Directory);
+                if is_directory {
+                    queue
+                        .expand_directory(&job, &source, &target, overwrite)
+                        .await;
+                } else {
+                    job.steps.borrow_mut().push_front(Step::Transfer {
+                        source,
+                        target,
+                        overwrite,
+                    });
+                    queue.run(&job);
+                }
+            }),
+            Some(Step::Transfer {
+                source,
+                target,
+                overwrite,
+            }) => self.transfer(&job, source, target, overwrite),
+            Some(Step::MakeDirectory { target, merge }) => spawn(async move {
+                match target.make_directory_future(glib::PRIORITY_DEFAULT).await {
+                    Err(error) if !(merge && error.matches(gio::IOErrorEnum::Exists)) => {
+                        queue.fail(&job, &target, &error)
+                    }
+                    _ => queue.run(&job),
+                }
+            }),
+            Some(Step::RemoveDirectory { source }) => spawn(async move {
+                match source.delete_future(glib::PRIORITY_DEFAULT).await {
+                    Ok(()) => queue.run(&job),
+                    Err(error) => queue.fail(&job, &source, &error),
+                }
+            }),
+        }
+    }
+
+    /// Queues making `target` and handling everything in `source` before the job's other steps.
+    async fn expand_directory(
+        &self,
+        job: &Rc<Job>,
+        source: &gio::File,
+        target: &gio::File,
+        overwrite: bool,
+    ) {
+        let children = match children(source, target, overwrite).await {
+            Ok(children) => children,
+            Err(error) => return self.fail(job, source, &error),
+        };
+        {
+            let mut steps = job.steps.borrow_mut();
+            if job.kind == JobKind::Move {
+                steps.push_front(Step::RemoveDirectory {
+                    source: source.clone(),
+                });
+            }
+            for child in children.into_iter().rev() {
+                steps.push_front(child);
+            }
+            steps.push_front(Step::MakeDirectory {
+                target: target.clone(),
+                merge: overwrite,
+            });
+        }
+        self.run(job);
+    }
+
+    async fn check_conflict(&self, job: &Rc<Job>, source: gio::File, target: gio::File) {
+        if target.equal(&source) {
+            // Pasting a copy next to the original keeps both, and moving it there does nothing
+            if job.kind == JobKind::Copy {
+                let target = unique_target(&target).await;
+                job.steps.borrow_mut().push_front(Step::Expand {
+                    source,
+                    target,
+                    overwrite: false,
+                });
+            }
+            return self.run(job);
+        }
+        if target.has_prefix(&source) {
+            let error = glib::Error::new(
+                gio::IOErrorEnum::InvalidArgument,
+                "A folder can't be put inside itself",
+            );
+            return self.fail(job, &source, &error);
+        }
+        if file_type(&target).await.is_none() {
+            job.steps.borrow_mut().push_front(Step::Expand {
+                source,
+                target,
+                overwrite: false,
+            });
+            return self.run(job);
+        }
+        // Without a window to ask in, the job can't go on
+        let window = match self.window.upgrade() {
+            Some(window) => window,
+            None => return self.finish(job, false),
+        };
+        let folder = target
+            .parent()
+            .map(|parent| display_name(&parent))
+            .unwrap_or_default();
+        let alert = gtk::AlertDialog::builder()
+            .modal(true)
+            .message(format!(
+                "{} already exists in {}",
+                display_name(&target),
+                folder
+            ))
+            .detail("Replacing a folder merges the two, replacing files with the same name.")
+            .buttons(["Cancel", "Skip", "Keep Both", "Replace"])
+            .cancel_button(0)
+            .default_button(1)
+            .build();
+        let step = match alert.choose_future(Some(&window)).await {
+            Ok(1) => {
+                job.skipped.set(true);
+                None
+            }
+            Ok(2) => Some(Step::Expand {
+                source,
+                target: unique_target(&target).await,
+                overwrite: false,
+            }),
+            Ok(3) => Some(Step::Expand {
+                source,
+                target,
+                overwrite: true,
+            }),
+            _ => {
+                job.cancellable.cancel();
+                None
+            }
+        };
+        if let Some(step) = step {
+            job.steps.borrow_mut().push_front(step);
+        }
+        self.run(job);
+    }
+
+    fn transfer(&self, job: &Rc<Job>, source: gio::File, target: gio::File, overwrite: bool) {
+        let mut flags = gio::FileCopyFlags::NOFOLLOW_SYMLINKS;
+        if overwrite {
+            flags |= gio::FileCopyFlags::OVERWRITE;
+        }
+        job.label.set_text(&format!(
+            "{} {}",
+            match job.kind {
+                JobKind::Copy => "Copying",
+                JobKind::Move => "Moving",
+            },
+            display_name(&source)
+        ));
+        job.progress.set_fraction(0.0);
+        let progress = job.progress.clone();
+        let on_progress: Box<dyn FnMut(i64, i64)> = Box::new(move |current, total| {
+            if total > 0 {
+                progress.set_fraction(current as f64 / total as f64);
+            }
+        });
+        let on_done = glib::clone!(@strong self as queue, @strong job, @strong source, @strong target => move |result: Result<(), glib::Error>| {
+            match result {
+                Ok(()) => queue.run(&job),
+                // Moving a folder between file systems, or into an existing one, has to be done
+                // file by file
+                Err(error)
+                    if job.kind == JobKind::Move
+                        && (error.matches(gio::IOErrorEnum::WouldRecurse)
+                            || error.matches(gio::IOErrorEnum::WouldMerge)) =>
+                {
+                    spawn(glib::clone!(@strong queue, @strong job, @strong source, @strong target => async move {
+                        queue.expand_directory(&job, &source, &target, overwrite).await;
+                    }));
+                }
+                Err(error) => queue.fail(&job, &source, &error),
+            }
+        });
+        match job.kind {
+            JobKind::Copy => source.copy_async(
+                &target,
+                flags,
+                glib::PRIORITY_DEFAULT,
+                Some(&job.cancellable),
+                Some(on_progress),
+                on_done,
+            ),
+            JobKind::Move => source.move_async(
+                &target,
+                flags,
+                glib::PRIORITY_DEFAULT,
+                Some(&job.cancellable),
+                Some(on_progress),
+                on_done,
+            ),
+        }
+    }
+}
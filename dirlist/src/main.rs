@@ -1,18 +1,25 @@
+mod clipboard;
 mod directory;
+mod filter;
+mod jobs;
+mod operations;
 
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     path::{Path, PathBuf},
     rc::Rc,
 };
 
 use gtk::{
+    gio,
     gio::{AppInfo, AppLaunchContext, FileInfo, FileType},
     glib,
     prelude::*,
 };
 
 use directory::DirectoryProvider;
+use jobs::{JobKind, JobQueue};
+use operations::show_error;
 
 const APP_ID: &str = "com.github.plish-plash.plash-gtk-apps.Dirlist";
 
@@ -26,16 +33,34 @@ pub fn expand_user_path(path: &Path) -> PathBuf {
     }
 }
 
-struct DirectoryWindow {
+/// A visited folder, with what's needed to show it again the way it was left.
+struct HistoryEntry {
+    path: PathBuf,
+    selection: Option<PathBuf>,
+    scroll: f64,
+}
+
+/// A folder view in one of a window's tabs, with its own path entry and history.
+struct DirectoryTab {
     app_window: gtk::ApplicationWindow,
+    notebook: gtk::Notebook,
+    root: gtk::Box,
+    label: gtk::Label,
     entry: gtk::Entry,
+    navigate_back: gtk::Button,
+    navigate_forward: gtk::Button,
     navigate_up: gtk::Button,
+    filter_button: gtk::ToggleButton,
     provider: DirectoryProvider,
     view: gtk::ColumnView,
+    jobs: JobQueue,
     queued_selection: RefCell<Option<PathBuf>>,
+    queued_scroll: Cell<Option<f64>>,
+    back: RefCell<Vec<HistoryEntry>>,
+    forward: RefCell<Vec<HistoryEntry>>,
 }
 
-impl DirectoryWindow {
+impl DirectoryTab {
     fn deselect(&self) {
         let model = self.view.model().and_downcast::<gtk::SingleSelection>();
         if let Some(model) = model {
@@ -61,23 +86,131 @@ impl DirectoryWindow {
             }
         }
     }
-    fn set_path(&self, path: &Path) {
+    fn dequeue_scroll(&self) {
+        if let (Some(scroll), Some(adjustment)) =
+            (self.queued_scroll.take(), self.view.vadjustment())
+        {
+            // Wait for the rows to be laid out, so the adjustment covers them
+            glib::idle_add_local_once(move || adjustment.set_value(scroll));
+        }
+    }
+    fn history_entry(&self) -> HistoryEntry {
+        HistoryEntry {
+            path: self.provider.path(),
+            selection: self
+                .selected_path()
+                .and_then(|path| path.file_name().map(PathBuf::from)),
+            scroll: self
+                .view
+                .vadjustment()
+                .map_or(0.0, |adjustment| adjustment.value()),
+        }
+    }
+    fn update_history_buttons(&self) {
+        self.navigate_back
+            .set_sensitive(!self.back.borrow().is_empty());
+        self.navigate_forward
+            .set_sensitive(!self.forward.borrow().is_empty());
+    }
+    /// Goes to `path`, remembering the current folder for going back.
+    fn navigate(&self, path: &Path) {
+        let entry = self.history_entry();
+        if self.set_path(path) && self.provider.path() != entry.path {
+            self.back.borrow_mut().push(entry);
+            self.forward.borrow_mut().clear();
+            self.update_history_buttons();
+        }
+    }
+    /// Goes back to a folder from the history. Returns false if it can't be shown anymore, which
+    /// drops it from the history while the current folder stays.
+    fn restore(&self, entry: HistoryEntry) -> bool {
+        self.queued_selection.replace(entry.selection);
+        self.queued_scroll.set(Some(entry.scroll));
+        if self.set_path(&entry.path) {
+            return true;
+        }
+        self.queued_selection.replace(None);
+        self.queued_scroll.set(None);
+        false
+    }
+    fn go_back(&self) {
+        let entry = self.back.borrow_mut().pop();
+        if let Some(entry) = entry {
+            let current = self.history_entry();
+            if self.restore(entry) {
+                self.forward.borrow_mut().push(current);
+            }
+            self.update_history_buttons();
+        }
+    }
+    fn go_forward(&self) {
+        let entry = self.forward.borrow_mut().pop();
+        if let Some(entry) = entry {
+            let current = self.history_entry();
+            if self.restore(entry) {
+                self.back.borrow_mut().push(current);
+            }
+            self.update_history_buttons();
+        }
+    }
+    fn go_up(&self) {
+        let mut path = self.provider.path();
+        if path.pop() {
+            self.navigate(&path);
+        }
+    }
+    fn selected_path(&self) -> Option<PathBuf> {
+        let item = self
+            .view
+            .model()
+            .and_downcast::<gtk::SingleSelection>()
+            .and_then(|model| model.selected_item())
+            .and_downcast::<FileInfo>()?;
+        Some(self.provider.path().join(item.name()))
+    }
+    fn copy_selected(&self, kind: JobKind) {
+        if let Some(path) = self.selected_path() {
+            let clipboard = self.app_window.clipboard();
+            if let Err(error) = clipboard::set_files(&clipboard, &[gio::File::for_path(path)], kind)
+            {
+                show_error(&self.app_window, "Error copying to the clipboard", &error);
+            }
+        }
+    }
+    fn paste(&self) {
+        let clipboard = self.app_window.clipboard();
+        let destination = gio::File::for_path(self.provider.path());
+        let jobs = self.jobs.clone();
+        clipboard::read_files(&self.app_window.clipboard(), move |files, kind| {
+            // Cut files can only be pasted once, as they're gone from where they were cut
+            let on_finished: Option<Box<dyn FnOnce()>> = match kind {
+                JobKind::Copy => None,
+                JobKind::Move => {
+                    let moved = files.clone();
+                    Some(Box::new(move || clipboard::clear_cut(&clipboard, moved)))
+                }
+            };
+            jobs.add(kind, files, &destination, on_finished);
+        });
+    }
+    fn is_current(&self) -> bool {
+        let page_num = self.notebook.page_num(&self.root);
+        page_num.is_some() && page_num == self.notebook.current_page()
+    }
+    /// Shows the folder `path`, or the folder containing it with it selected. Returns false if
+    /// the path isn't valid.
+    fn set_path(&self, path: &Path) -> bool {
         let mut path = if let Ok(path) = expand_user_path(path).canonicalize() {
             path
         } else {
-            let alert = gtk::AlertDialog::builder()
-                .modal(true)
-                .message("Invalid path")
-                .detail(path.to_string_lossy())
-                .build();
-            alert.show(Some(&self.app_window));
-            return;
+            show_error(&self.app_window, "Invalid path", &path.to_string_lossy());
+            return false;
         };
         if !path.is_dir() {
             let file_name = if let Some(file_name) = path.file_name() {
                 Path::new(file_name).to_owned()
             } else {
-                return;
+                return false;
             };
             path.pop();
             self.queued_selection.replace(Some(file_name));
@@ -88,22 +221,96 @@ impl DirectoryWindow {
             .file_name()
             .unwrap_or(path.as_os_str())
             .to_string_lossy();
-        self.app_window.set_title(Some(&title));
+        self.label.set_text(&title);
+        if self.is_current() {
+            self.app_window.set_title(Some(&title));
+        }
         self.entry.set_text(&path.to_string_lossy());
         self.navigate_up.set_sensitive(path.parent().is_some());
         self.provider.set_path(&path);
         if !self.provider.directory.is_loading() {
             self.dequeue_selection();
+            self.dequeue_scroll();
+        }
+        true
+    }
+}
+
+struct DirectoryWindow {
+    app_window: gtk::ApplicationWindow,
+    notebook: gtk::Notebook,
+    jobs: JobQueue,
+    tabs: RefCell<Vec<Rc<DirectoryTab>>>,
+    /// Shared by all tabs, and given to new ones.
+    show_hidden: Cell<bool>,
+}
+
+impl DirectoryWindow {
+    fn tab_for_page(&self, page: &gtk::Widget) -> Option<Rc<DirectoryTab>> {
+        self.tabs
+            .borrow()
+            .iter()
+            .find(|tab| tab.root.upcast_ref::<gtk::Widget>() == page)
+            .cloned()
+    }
+    fn current_tab(&self) -> Option<Rc<DirectoryTab>> {
+        let page = self.notebook.nth_page(self.notebook.current_page())?;
+        self.tab_for_page(&page)
+    }
+    fn close_tab(&self, tab: &DirectoryTab) {
+        if let Some(page_num) = self.notebook.page_num(&tab.root) {
+            self.notebook.remove_page(Some(page_num));
+        }
+        self.tabs
+            .borrow_mut()
+            .retain(|other| other.root != tab.root);
+        if self.tabs.borrow().is_empty() {
+            self.app_window.close();
         }
     }
 }
 
-fn open_window(app: &gtk::Application, path: &Path) {
+/// Adds a tab showing `path` after the current one, and switches to it. Returns false, without
+/// adding a tab, if the path isn't valid.
+fn open_tab(window: &Rc<DirectoryWindow>, path: &Path) -> bool {
     const SPACING: i32 = 6;
+    let path = match expand_user_path(path).canonicalize() {
+        Ok(path) => path,
+        Err(error) => {
+            let detail = format!("{}: {}", path.to_string_lossy(), error);
+            show_error(&window.app_window, "Invalid path", &detail);
+            return false;
+        }
+    };
+    // For a file, the tab shows its folder with the file selected
+    let folder = match path.parent() {
+        Some(parent) if !path.is_dir() => parent,
+        _ => path.as_path(),
+    };
     let root = gtk::Box::new(gtk::Orientation::Vertical, SPACING);
 
+    let navigate_back = gtk::Button::builder()
+        .icon_name("go-previous")
+        .tooltip_text("Back")
+        .sensitive(false)
+        .build();
+    let navigate_forward = gtk::Button::builder()
+        .icon_name("go-next")
+        .tooltip_text("Forward")
+        .sensitive(false)
+        .build();
     let entry = gtk::Entry::builder().hexpand(true).build();
     let navigate_up = gtk::Button::from_icon_name("go-up");
+    let menu = gio::Menu::new();
+    menu.append(Some("New Tab"), Some("win.new-tab"));
+    menu.append(Some("New Folder…"), Some("win.new-folder"));
+    menu.append(Some("New File…"), Some("win.new-file"));
+    menu.append(Some("Paste"), Some("win.paste"));
+    menu.append(Some("Show Hidden Files"), Some("win.show-hidden"));
+    let menu_button = gtk::MenuButton::builder()
+        .icon_name("open-menu-symbolic")
+        .menu_model(&menu)
+        .build();
     let entry_row = gtk::Box::builder()
         .orientation(gtk::Orientation::Horizontal)
         .spacing(SPACING)
@@ -111,73 +318,286 @@ fn open_window(app: &gtk::Application, path: &Path) {
         .margin_start(SPACING)
         .margin_end(SPACING)
         .build();
+    entry_row.append(&navigate_back);
+    entry_row.append(&navigate_forward);
     entry_row.append(&entry);
     entry_row.append(&navigate_up);
+    let filter_button = gtk::ToggleButton::builder()
+        .icon_name("edit-find-symbolic")
+        .tooltip_text("Filter")
+        .build();
+    entry_row.append(&filter_button);
+    entry_row.append(&menu_button);
     root.append(&entry_row);
 
-    let provider = DirectoryProvider::new(path);
+    let provider = DirectoryProvider::new(folder);
+    provider.set_show_hidden(window.show_hidden.get());
+    let filter_bar = filter::build_filter_bar(&provider);
+    filter_button
+        .bind_property("active", &filter_bar, "reveal-child")
+        .build();
+    root.append(&filter_bar);
     let (pane, view) = gtk_list_provider::build_column_view(&provider, 240);
     root.append(&pane);
 
-    let app_window = gtk::ApplicationWindow::builder()
-        .application(app)
-        .icon_name("system-file-manager")
-        .default_width(640)
-        .default_height(480)
-        .child(&root)
+    let label = gtk::Label::builder()
+        .max_width_chars(24)
+        .ellipsize(gtk::pango::EllipsizeMode::Middle)
         .build();
+    let close = gtk::Button::builder()
+        .icon_name("window-close-symbolic")
+        .tooltip_text("Close Tab")
+        .css_classes(["flat"])
+        .build();
+    let tab_label = gtk::Box::new(gtk::Orientation::Horizontal, SPACING);
+    tab_label.append(&label);
+    tab_label.append(&close);
 
-    let window = Rc::new(DirectoryWindow {
-        app_window,
+    let tab = Rc::new(DirectoryTab {
+        app_window: window.app_window.clone(),
+        notebook: window.notebook.clone(),
+        root,
+        label,
         entry,
+        navigate_back,
+        navigate_forward,
         navigate_up,
+        filter_button,
         provider,
         view,
+        jobs: window.jobs.clone(),
         queued_selection: RefCell::new(None),
+        queued_scroll: Cell::new(None),
+        back: RefCell::default(),
+        forward: RefCell::default(),
     });
-    window.set_path(path);
-    window.app_window.present();
+    window.tabs.borrow_mut().push(tab.clone());
+    let position = window.notebook.current_page().map(|page| page + 1);
+    let page_num = window
+        .notebook
+        .insert_page(&tab.root, Some(&tab_label), position);
+    window.notebook.set_tab_reorderable(&tab.root, true);
+    tab.set_path(&path);
+    window.notebook.set_current_page(Some(page_num));
 
-    window
-        .entry
-        .connect_activate(glib::clone!(@strong window => move |_| {
-            let entry_text = window.entry.text();
-            window.set_path(Path::new(&entry_text));
-        }));
-    window
-        .navigate_up
-        .connect_clicked(glib::clone!(@strong window => move |_| {
-            let mut path = window.provider.path();
-            if path.pop() {
-                window.set_path(&path);
-            }
+    close.connect_clicked(glib::clone!(@weak window, @weak tab => move |_| {
+        window.close_tab(&tab);
+    }));
+    tab.entry
+        .connect_activate(glib::clone!(@weak tab => move |_| {
+            let entry_text = tab.entry.text();
+            tab.navigate(Path::new(&entry_text));
         }));
-    window.view.connect_activate(glib::clone!(@strong window => move |view, position| {
+    tab.navigate_back
+        .connect_clicked(glib::clone!(@weak tab => move |_| tab.go_back()));
+    tab.navigate_forward
+        .connect_clicked(glib::clone!(@weak tab => move |_| tab.go_forward()));
+    tab.navigate_up
+        .connect_clicked(glib::clone!(@weak tab => move |_| tab.go_up()));
+    tab.view.connect_activate(glib::clone!(@weak tab => move |view, position| {
         let item = view
             .model()
             .unwrap()
             .item(position)
             .and_downcast::<FileInfo>();
         if let Some(item) = item {
-            let mut path = window.provider.path();
+            let mut path = tab.provider.path();
             path.push(item.name());
             if item.file_type() == FileType::Directory {
-                window.set_path(&path);
+                tab.navigate(&path);
             } else {
                 let uri = format!("file://{}", path.to_string_lossy());
                 if let Err(error) = AppInfo::launch_default_for_uri(&uri, AppLaunchContext::NONE) {
-                    eprintln!("{}", error);
+                    show_error(&tab.app_window, "Error opening file", &error.to_string());
                 }
             }
         }
     }));
-    window.provider.directory.connect_loading_notify(
-        glib::clone!(@strong window => move |directory| {
+
+    // Only while the list has focus, so these keys still edit the path entry
+    let shortcuts = gtk::ShortcutController::new();
+    for (trigger, action) in [
+        ("F2", "win.rename"),
+        ("Delete", "win.trash"),
+        ("<Shift>Delete", "win.delete"),
+        ("<Control>c", "win.copy"),
+        ("<Control>x", "win.cut"),
+        ("<Control>v", "win.paste"),
+    ] {
+        shortcuts.add_shortcut(gtk::Shortcut::new(
+            gtk::ShortcutTrigger::parse_string(trigger),
+            Some(gtk::NamedAction::new(action)),
+        ));
+    }
+    tab.view.add_controller(shortcuts);
+
+    tab.provider
+        .directory
+        .connect_loading_notify(glib::clone!(@weak tab => move |directory| {
             if !directory.is_loading() {
-                window.dequeue_selection();
+                tab.dequeue_selection();
+                tab.dequeue_scroll();
             }
+        }));
+    true
+}
+
+/// Opens a window with a tab for each of `paths`.
+fn open_window(app: &gtk::Application, paths: &[PathBuf]) {
+    let notebook = gtk::Notebook::builder().scrollable(true).build();
+    let app_window = gtk::ApplicationWindow::builder()
+        .application(app)
+        .icon_name("system-file-manager")
+        .default_width(640)
+        .default_height(480)
+        .child(&notebook)
+        .build();
+    let jobs = JobQueue::new(&app_window);
+    let new_tab = gtk::Button::builder()
+        .icon_name("tab-new-symbolic")
+        .tooltip_text("New Tab")
+        .action_name("win.new-tab")
+        .css_classes(["flat"])
+        .build();
+    let notebook_actions = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+    notebook_actions.append(&jobs.button);
+    notebook_actions.append(&new_tab);
+    notebook.set_action_widget(&notebook_actions, gtk::PackType::End);
+
+    let window = Rc::new(DirectoryWindow {
+        app_window,
+        notebook,
+        jobs,
+        tabs: RefCell::default(),
+        show_hidden: Cell::new(false),
+    });
+    window
+        .notebook
+        .connect_switch_page(glib::clone!(@weak window => move |_, page, _| {
+            if let Some(tab) = window.tab_for_page(page) {
+                window.app_window.set_title(Some(&tab.label.text()));
+            }
+        }));
+    let mut opened = false;
+    for path in paths {
+        opened |= open_tab(&window, path);
+    }
+    // The window stays open to show why, starting from the home folder instead
+    if !opened {
+        open_tab(&window, &glib::home_dir());
+    }
+    window.app_window.present();
+
+    let new_tab = gio::SimpleAction::new("new-tab", None);
+    new_tab.connect_activate(glib::clone!(@weak window => move |_, _| {
+        let path = window
+            .current_tab()
+            .map_or_else(glib::home_dir, |tab| tab.provider.path());
+        open_tab(&window, &path);
+    }));
+    window.app_window.add_action(&new_tab);
+    let open_in_new_tab = gio::SimpleAction::new("open-in-new-tab", Some(glib::VariantTy::STRING));
+    open_in_new_tab.connect_activate(glib::clone!(@weak window => move |_, parameter| {
+        let uri = parameter.and_then(|parameter| parameter.get::<String>());
+        if let Some(path) = uri.and_then(|uri| gio::File::for_uri(&uri).path()) {
+            open_tab(&window, &path);
+        }
+    }));
+    window.app_window.add_action(&open_in_new_tab);
+    let close_tab = gio::SimpleAction::new("close-tab", None);
+    close_tab.connect_activate(glib::clone!(@weak window => move |_, _| {
+        if let Some(tab) = window.current_tab() {
+            window.close_tab(&tab);
+        }
+    }));
+    window.app_window.add_action(&close_tab);
+
+    let show_hidden = gio::SimpleAction::new_stateful("show-hidden", None, &false.to_variant());
+    show_hidden.connect_activate(glib::clone!(@weak window => move |action, _| {
+        let show_hidden = !window.show_hidden.get();
+        window.show_hidden.set(show_hidden);
+        action.set_state(&show_hidden.to_variant());
+        for tab in window.tabs.borrow().iter() {
+            tab.provider.set_show_hidden(show_hidden);
+        }
+    }));
+    window.app_window.add_action(&show_hidden);
+
+    // The remaining actions act on the current tab
+    let tab_actions: [(&str, fn(&DirectoryTab)); 7] = [
+        ("back", DirectoryTab::go_back),
+        ("forward", DirectoryTab::go_forward),
+        ("up", DirectoryTab::go_up),
+        ("copy", |tab| tab.copy_selected(JobKind::Copy)),
+        ("cut", |tab| tab.copy_selected(JobKind::Move)),
+        ("paste", DirectoryTab::paste),
+        ("filter", |tab| {
+            tab.filter_button.set_active(!tab.filter_button.is_active())
         }),
-    );
+    ];
+    for (name, activate) in tab_actions {
+        let action = gio::SimpleAction::new(name, None);
+        action.connect_activate(glib::clone!(@weak window => move |_, _| {
+            if let Some(tab) = window.current_tab() {
+                activate(&tab);
+            }
+        }));
+        window.app_window.add_action(&action);
+    }
+    // The back and forward buttons found on many mice
+    let mouse_buttons = gtk::GestureClick::builder()
+        .button(0)
+        .propagation_phase(gtk::PropagationPhase::Capture)
+        .build();
+    mouse_buttons.connect_pressed(glib::clone!(@weak window => move |gesture, _, _, _| {
+        let tab = match window.current_tab() {
+            Some(tab) => tab,
+            None => return,
+        };
+        match gesture.current_button() {
+            8 => tab.go_back(),
+            9 => tab.go_forward(),
+            _ => return,
+        }
+        gesture.set_state(gtk::EventSequenceState::Claimed);
+    }));
+    window.app_window.add_controller(mouse_buttons);
+
+    // File operations act on the selected file, or create files in the current folder
+    let selected_actions: [(&str, fn(&gtk::Window, &Path)); 3] = [
+        ("rename", operations::rename),
+        ("trash", operations::trash),
+        ("delete", operations::delete),
+    ];
+    for (name, operation) in selected_actions {
+        let action = gio::SimpleAction::new(name, None);
+        action.connect_activate(glib::clone!(@weak window => move |_, _| {
+            if let Some(path) = window.current_tab().and_then(|tab| tab.selected_path()) {
+                operation(window.app_window.upcast_ref(), &path);
+            }
+        }));
+        window.app_window.add_action(&action);
+    }
+    let directory_actions: [(&str, fn(&gtk::Window, &Path)); 2] = [
+        ("new-folder", operations::new_folder),
+        ("new-file", operations::new_file),
+    ];
+    for (name, operation) in directory_actions {
+        let action = gio::SimpleAction::new(name, None);
+        action.connect_activate(glib::clone!(@weak window => move |_, _| {
+            if let Some(tab) = window.current_tab() {
+                operation(window.app_window.upcast_ref(), &tab.provider.path());
+            }
+        }));
+        window.app_window.add_action(&action);
+    }
+
+    // Everything else holds the window weakly, so this keeps it alive until it's closed
+    window
+        .app_window
+        .connect_destroy(glib::clone!(@strong window => move |_| {
+            window.tabs.borrow_mut().clear();
+        }));
 }
 
 fn main() -> glib::ExitCode {
@@ -185,15 +605,25 @@ fn main() -> glib::ExitCode {
         .application_id(APP_ID)
         .flags(gtk::gio::ApplicationFlags::HANDLES_OPEN)
         .build();
-    app.connect_startup(|_| gtk_list_provider::load_css());
+    app.connect_startup(|app| {
+        gtk_list_provider::load_css();
+        app.set_accels_for_action("win.new-folder", &["<Control><Shift>n"]);
+        app.set_accels_for_action("win.back", &["<Alt>Left"]);
+        app.set_accels_for_action("win.forward", &["<Alt>Right"]);
+        app.set_accels_for_action("win.up", &["<Alt>Up"]);
+        app.set_accels_for_action("win.new-tab", &["<Control>t"]);
+        app.set_accels_for_action("win.close-tab", &["<Control>w"]);
+        app.set_accels_for_action("win.show-hidden", &["<Control>h"]);
+        app.set_accels_for_action("win.filter", &["<Control>f"]);
+    });
     app.connect_activate(|app| {
         let path = std::env::current_dir().expect("couldn't get current directory");
-        open_window(app, &path);
+        open_window(app, &[path]);
     });
     app.connect_open(|app, files, _hint| {
-        for file in files {
-            let path = file.path().unwrap();
-            open_window(app, &path);
+        let paths: Vec<PathBuf> = files.iter().filter_map(|file| file.path()).collect();
+        if !paths.is_empty() {
+            open_window(app, &paths);
         }
     });
     app.run()
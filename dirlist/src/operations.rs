@@ -0,0 +1,167 @@
+use std::path::{Path, PathBuf};
+
+use gtk::{gio, glib, prelude::*};
+
+pub fn show_error(window: &impl IsA<gtk::Window>, message: &str, detail: &str) {
+    let alert = gtk::AlertDialog::builder()
+        .modal(true)
+        .message(message)
+        .detail(detail)
+        .build();
+    alert.show(Some(window));
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn validate_name(name: &str) -> Result<&str, String> {
+    let name = name.trim();
+    if name.is_empty() || name == "." || name == ".." {
+        return Err(String::from("Enter a name"));
+    }
+    if name.contains('/') {
+        return Err(String::from("Names can't contain \"/\""));
+    }
+    Ok(name)
+}
+
+/// Asks for a file name. The dialog stays open with an error if `on_accept` fails.
+fn prompt_name(
+    parent: &gtk::Window,
+    title: &str,
+    accept_label: &str,
+    initial: &str,
+    on_accept: impl Fn(&str) -> Result<(), String> + 'static,
+) {
+    let entry = gtk::Entry::builder()
+        .text(initial)
+        .activates_default(true)
+        .margin_top(12)
+        .margin_bottom(12)
+        .margin_start(12)
+        .margin_end(12)
+        .build();
+    let cancel = gtk::Button::with_label("Cancel");
+    let accept = gtk::Button::builder()
+        .label(accept_label)
+        .css_classes(["suggested-action"])
+        .build();
+    let header = gtk::HeaderBar::builder().show_title_buttons(false).build();
+    header.pack_start(&cancel);
+    header.pack_end(&accept);
+    let dialog = gtk::Window::builder()
+        .title(title)
+        .transient_for(parent)
+        .modal(true)
+        .default_width(360)
+        .titlebar(&header)
+        .child(&entry)
+        .default_widget(&accept)
+        .build();
+
+    cancel.connect_clicked(glib::clone!(@weak dialog => move |_| dialog.close()));
+    accept.connect_clicked(glib::clone!(@weak dialog, @weak entry => move |_| {
+        let result = validate_name(&entry.text()).and_then(|name| on_accept(name));
+        match result {
+            Ok(()) => dialog.close(),
+            Err(error) => show_error(&dialog, "Invalid name", &error),
+        }
+    }));
+    dialog.present();
+
+    // Select the name without its extension, so typing replaces only that
+    let stem = Path::new(initial)
+        .file_stem()
+        .map_or(0, |stem| stem.to_string_lossy().chars().count());
+    entry.grab_focus();
+    entry.select_region(0, stem as i32);
+}
+
+pub fn rename(parent: &gtk::Window, path: &Path) {
+    let file = gio::File::for_path(path);
+    prompt_name(parent, "Rename", "Rename", &file_name(path), move |name| {
+        file.set_display_name(name, gio::Cancellable::NONE)
+            .map(|_| ())
+            .map_err(|error| error.to_string())
+    });
+}
+
+pub fn new_folder(parent: &gtk::Window, directory: &Path) {
+    let directory = directory.to_owned();
+    prompt_name(parent, "New Folder", "Create", "New Folder", move |name| {
+        gio::File::for_path(directory.join(name))
+            .make_directory(gio::Cancellable::NONE)
+            .map_err(|error| error.to_string())
+    });
+}
+
+pub fn new_file(parent: &gtk::Window, directory: &Path) {
+    let directory = directory.to_owned();
+    prompt_name(parent, "New File", "Create", "", move |name| {
+        // The stream is closed when dropped, leaving an empty file
+        gio::File::for_path(directory.join(name))
+            .create(gio::FileCreateFlags::NONE, gio::Cancellable::NONE)
+            .map(|_| ())
+            .map_err(|error| error.to_string())
+    });
+}
+
+pub fn trash(parent: &gtk::Window, path: &Path) {
+    if let Err(error) = gio::File::for_path(path).trash(gio::Cancellable::NONE) {
+        show_error(
+            parent,
+            &format!("Error moving {} to the trash", file_name(path)),
+            &error.to_string(),
+        );
+    }
+}
+
+fn delete_now(path: &Path) -> Result<(), String> {
+    let map_err = |error: std::io::Error| error.to_string();
+    // Not following symlinks, so a link to a folder deletes only the link
+    match path.symlink_metadata() {
+        Ok(metadata) if metadata.is_dir() => std::fs::remove_dir_all(path).map_err(map_err),
+        _ => std::fs::remove_file(path).map_err(map_err),
+    }
+}
+
+/// Deletes `path` without the trash, after asking first. Folders are deleted with everything in
+/// them.
+pub fn delete(parent: &gtk::Window, path: &Path) {
+    let path: PathBuf = path.to_owned();
+    let alert = gtk::AlertDialog::builder()
+        .modal(true)
+        .message(format!("Permanently delete {}?", file_name(&path)))
+        .detail("It will be gone for good, instead of moved to the trash.")
+        .buttons(["Cancel", "Delete"])
+        .cancel_button(0)
+        .default_button(0)
+        .build();
+    alert.choose(
+        Some(parent),
+        gio::Cancellable::NONE,
+        glib::clone!(@weak parent => move |result| {
+            if !matches!(result, Ok(1)) {
+                return;
+            }
+            // Deleting a large folder takes a while, so it's done on another thread
+            glib::MainContext::default().spawn_local(async move {
+                let deleted = gio::spawn_blocking(glib::clone!(@strong path => move || {
+                    delete_now(&path)
+                }))
+                .await
+                .unwrap_or_else(|_| Err(String::from("Deleting stopped unexpectedly")));
+                if let Err(error) = deleted {
+                    show_error(
+                        &parent,
+                        &format!("Error deleting {}", file_name(&path)),
+                        &error,
+                    );
+                }
+            });
+        }),
+    );
+}
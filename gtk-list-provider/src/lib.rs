@@ -1,4 +1,5 @@
 use std::fmt::Display;
+use std::rc::Rc;
 
 use gtk::glib;
 use gtk::prelude::*;
@@ -7,10 +8,21 @@ pub trait ListContent: 'static {
     type ModelItem: IsA<gtk::glib::Object>;
     fn setup_content(&self) -> gtk::Widget;
     fn bind_content(&self, widget: gtk::Widget, item: Self::ModelItem);
+    /// Called instead of `bind_content` when the model reports that the shown item changed,
+    /// which can be an update to the same item. Binds it again unless overridden.
+    fn update_content(&self, widget: gtk::Widget, item: Self::ModelItem) {
+        self.bind_content(widget, item);
+    }
 }
 
 pub trait ListColumn: ListContent + Clone + Display {
     fn sort(&self, a: &Self::ModelItem, b: &Self::ModelItem) -> gtk::Ordering;
+    fn context_menu(&self, _item: &Self::ModelItem) -> Option<gtk::gio::MenuModel> {
+        None
+    }
+    /// Called when a row is clicked with the middle mouse button. `widget` is the clicked cell,
+    /// for activating actions.
+    fn middle_click(&self, _item: &Self::ModelItem, _widget: &gtk::Widget) {}
 }
 
 pub trait ListProvider {
@@ -28,6 +40,43 @@ fn list_item_factory_for_column<C: ListColumn>(column: C) -> gtk::SignalListItem
     list_item_factory.connect_setup(glib::clone!(@strong column => move |_factory, object| {
         let list_item: &gtk::ListItem = object.downcast_ref().unwrap();
         let child = column.setup_content();
+
+        // Right-click selects the row and shows its context menu
+        let right_click = gtk::GestureClick::new();
+        right_click.set_button(gtk::gdk::ffi::GDK_BUTTON_SECONDARY as u32);
+        right_click.connect_pressed(glib::clone!(@strong column, @weak list_item, @weak child => move |gesture, _, x, y| {
+            let item = list_item.item().and_downcast::<C::ModelItem>();
+            let menu = item.and_then(|item| column.context_menu(&item));
+            if let Some(menu) = menu {
+                gesture.set_state(gtk::EventSequenceState::Claimed);
+                let _ = child.activate_action("listitem.select", Some(&(false, false).to_variant()));
+                let popover = gtk::PopoverMenu::builder()
+                    .menu_model(&menu)
+                    .has_arrow(false)
+                    .pointing_to(&gtk::gdk::Rectangle::new(x as i32, y as i32, 1, 1))
+                    .build();
+                popover.set_parent(&child);
+                popover.connect_closed(|popover| {
+                    // Unparent later, so the activated menu item can still find its action
+                    glib::idle_add_local_once(glib::clone!(@weak popover => move || {
+                        popover.unparent();
+                    }));
+                });
+                popover.popup();
+            }
+        }));
+        child.add_controller(right_click);
+
+        let middle_click = gtk::GestureClick::new();
+        middle_click.set_button(gtk::gdk::ffi::GDK_BUTTON_MIDDLE as u32);
+        middle_click.connect_pressed(glib::clone!(@strong column, @weak list_item, @weak child => move |gesture, _, _, _| {
+            if let Some(item) = list_item.item().and_downcast::<C::ModelItem>() {
+                gesture.set_state(gtk::EventSequenceState::Claimed);
+                column.middle_click(&item, &child);
+            }
+        }));
+        child.add_controller(middle_click);
+
         list_item.set_child(Some(&child));
     }));
     list_item_factory.connect_bind(move |_factory, object| {
@@ -57,13 +106,27 @@ fn build_detail_pane<P: ListProvider, V: IsA<gtk::Widget>>(
         .visible(false)
         .build();
 
-    model.connect_selection_changed(glib::clone!(@strong detail_scroll => move |model, _, _| {
-        let item = model.selected_item().and_downcast::<P::ModelItem>();
-        detail_scroll.set_visible(item.is_some());
-        if let Some(item) = item {
-            detail.bind_content(detail_widget.clone(), item);
-        }
+    let bind_selected = Rc::new(
+        glib::clone!(@strong detail_scroll => move |model: &gtk::SingleSelection, updated: bool| {
+            let item = model.selected_item().and_downcast::<P::ModelItem>();
+            detail_scroll.set_visible(item.is_some());
+            match item {
+                Some(item) if updated => detail.update_content(detail_widget.clone(), item),
+                Some(item) => detail.bind_content(detail_widget.clone(), item),
+                None => {}
+            }
+        }),
+    );
+    model.connect_selection_changed(glib::clone!(@strong bind_selected => move |model, _, _| {
+        bind_selected(model, false);
     }));
+    // Update the detail when the selected item itself changes, not only when the selection moves.
+    model.connect_items_changed(move |model, position, _removed, added| {
+        let selected = model.selected();
+        if selected >= position && selected < position + added {
+            bind_selected(model, true);
+        }
+    });
 
     // Right-click deselect
     // let right_click = gtk::GestureClick::new();
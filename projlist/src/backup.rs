@@ -0,0 +1,398 @@
+use std::{
+    cell::RefCell,
+    ffi::OsStr,
+    io::Write,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use gtk::{glib, prelude::*};
+
+use crate::{
+    config::{self, ProjectInfo},
+    show_error, ProjectProvider, APP_CONFIG_DIR,
+};
+
+const MAX_BACKUPS: usize = 30;
+
+/// Each projects file gets its own folder of backups, named after the file and a hash of its full
+/// path, so files with the same name in different folders don't share one.
+fn backup_dir(projects_file: &Path) -> PathBuf {
+    let path = projects_file
+        .canonicalize()
+        .unwrap_or_else(|_| projects_file.to_owned());
+    let hash =
+        glib::compute_checksum_for_data(glib::ChecksumType::Sha256, path.as_os_str().as_bytes())
+            .unwrap_or_default();
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    glib::user_data_dir()
+        .join(APP_CONFIG_DIR)
+        .join("backups")
+        .join(format!("{}-{}", file_name, hash))
+}
+
+/// Backups keep the extension so they're read with the same store as the original.
+fn backup_extension(projects_file: &Path) -> String {
+    projects_file
+        .extension()
+        .map(|e| e.to_string_lossy().into_owned())
+        .unwrap_or_else(|| String::from("toml"))
+}
+
+/// Backups of `projects_file`, newest first.
+pub fn list_backups(projects_file: &Path) -> Vec<PathBuf> {
+    list_backups_in(&backup_dir(projects_file), &backup_extension(projects_file))
+}
+
+fn list_backups_in(dir: &Path, extension: &str) -> Vec<PathBuf> {
+    let mut backups: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.extension() == Some(OsStr::new(extension)))
+                .collect()
+        })
+        .unwrap_or_default();
+    // Timestamped names sort in the order they were taken
+    backups.sort();
+    backups.reverse();
+    backups
+}
+
+/// Copies `projects_file` into its backup folder, unless it matches the newest backup, and
+/// deletes the oldest backups beyond `MAX_BACKUPS`.
+pub fn backup(projects_file: &Path) -> Result<(), String> {
+    backup_into(&backup_dir(projects_file), projects_file)
+}
+
+fn backup_into(dir: &Path, projects_file: &Path) -> Result<(), String> {
+    let extension = backup_extension(projects_file);
+    let backups = list_backups_in(dir, &extension);
+    let newest = backups.first();
+    let contents = match std::fs::read(projects_file) {
+        Ok(contents) => contents,
+        Err(_) => return Ok(()),
+    };
+    if newest
+        .and_then(|newest| std::fs::read(newest).ok())
+        .as_ref()
+        == Some(&contents)
+    {
+        return Ok(());
+    }
+    let map_err = |error| format!("Error backing up {}: {}", projects_file.display(), error);
+    std::fs::create_dir_all(dir).map_err(map_err)?;
+    // Down to the microsecond, so backups taken within the same second don't share a name
+    let timestamp = glib::DateTime::now_local()
+        .and_then(|now| now.format("%Y%m%d-%H%M%S-%f"))
+        .map_err(|error| format!("Error backing up {}: {}", projects_file.display(), error))?;
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(dir.join(format!("{}.{}", timestamp, extension)))
+        .and_then(|mut file| file.write_all(&contents))
+        .map_err(map_err)?;
+    for old in list_backups_in(dir, &extension).iter().skip(MAX_BACKUPS) {
+        std::fs::remove_file(old).map_err(map_err)?;
+    }
+    Ok(())
+}
+
+/// How restoring a backup would change the projects file.
+#[derive(Default)]
+struct Diff {
+    added: Vec<String>,
+    removed: Vec<String>,
+    changed: Vec<(String, Vec<String>)>,
+}
+
+fn changed_keys(current: &ProjectInfo, backup: &ProjectInfo) -> Vec<String> {
+    let table = |project: &ProjectInfo| match toml::Value::try_from(project) {
+        Ok(toml::Value::Table(table)) => table,
+        _ => toml::map::Map::new(),
+    };
+    let current = table(current);
+    let backup = table(backup);
+    let mut keys: Vec<String> = current
+        .keys()
+        .chain(backup.keys())
+        .filter(|key| current.get(*key) != backup.get(*key))
+        .cloned()
+        .collect();
+    keys.sort();
+    keys.dedup();
+    keys
+}
+
+fn diff(current: &[ProjectInfo], backup: &[ProjectInfo]) -> Diff {
+    let mut diff = Diff::default();
+    for project in backup {
+        match current.iter().find(|p| p.name == project.name) {
+            Some(current) if current != project => diff
+                .changed
+                .push((project.name.clone(), changed_keys(current, project))),
+            Some(_) => {}
+            None => diff.added.push(project.name.clone()),
+        }
+    }
+    for project in current {
+        if !backup.iter().any(|p| p.name == project.name) {
+            diff.removed.push(project.name.clone());
+        }
+    }
+    diff
+}
+
+fn describe_diff(projects_file: &Path, collection: &str, backup_file: &Path) -> String {
+    let current = config::load_projects(projects_file, collection).unwrap_or_default();
+    let backup = match config::load_projects(backup_file, collection) {
+        Ok(backup) => backup,
+        Err(error) => return error,
+    };
+    let diff = diff(&current, &backup);
+    let mut text = String::new();
+    let mut section = |heading: &str, lines: Vec<String>| {
+        if !lines.is_empty() {
+            text.push_str(&format!("{}\n", heading));
+            for line in lines {
+                text.push_str(&format!("  {}\n", line));
+            }
+        }
+    };
+    section("Restoring adds:", diff.added);
+    section("Restoring removes:", diff.removed);
+    section(
+        "Restoring changes:",
+        diff.changed
+            .into_iter()
+            .map(|(name, keys)| format!("{} ({})", name, keys.join(", ")))
+            .collect(),
+    );
+    if text.is_empty() {
+        text.push_str("The backup matches the current projects.");
+    }
+    text
+}
+
+fn backup_label(backup_file: &Path) -> String {
+    let stem = backup_file
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    // Shown as "YYYY-MM-DD HH:MM:SS" rather than the file name's compact form
+    match (
+        stem.get(0..4),
+        stem.get(4..6),
+        stem.get(6..8),
+        stem.get(9..11),
+        stem.get(11..13),
+        stem.get(13..15),
+    ) {
+        (Some(y), Some(mo), Some(d), Some(h), Some(mi), Some(s)) => {
+            format!("{}-{}-{} {}:{}:{}", y, mo, d, h, mi, s)
+        }
+        _ => stem,
+    }
+}
+
+fn refresh_backups(list: &gtk::ListBox, backups: &RefCell<Vec<PathBuf>>, projects_file: &Path) {
+    while let Some(child) = list.first_child() {
+        list.remove(&child);
+    }
+    backups.replace(list_backups(projects_file));
+    for backup_file in backups.borrow().iter() {
+        let label = gtk::Label::builder()
+            .label(backup_label(backup_file))
+            .xalign(0.0)
+            .build();
+        list.append(&label);
+    }
+}
+
+pub fn show_restore_dialog(app_window: &gtk::ApplicationWindow, provider: &ProjectProvider) {
+    let projects_files: Vec<(String, PathBuf)> = provider
+        .app_config
+        .borrow()
+        .collections()
+        .iter()
+        .map(|c| (c.name.clone(), c.projects_file()))
+        .collect();
+    let names: Vec<&str> = projects_files
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect();
+    let collection = gtk::DropDown::from_strings(&names);
+    collection.set_visible(projects_files.len() > 1);
+
+    let list = gtk::ListBox::new();
+    let list_scrolled = gtk::ScrolledWindow::builder()
+        .hscrollbar_policy(gtk::PolicyType::Never)
+        .min_content_width(180)
+        .vexpand(true)
+        .child(&list)
+        .build();
+    let diff_view = gtk::TextView::builder()
+        .editable(false)
+        .monospace(true)
+        .hexpand(true)
+        .build();
+    let diff_scrolled = gtk::ScrolledWindow::builder().child(&diff_view).build();
+    let pane = gtk::Paned::builder()
+        .start_child(&list_scrolled)
+        .end_child(&diff_scrolled)
+        .vexpand(true)
+        .build();
+    let content = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(6)
+        .margin_top(6)
+        .margin_bottom(6)
+        .margin_start(6)
+        .margin_end(6)
+        .build();
+    content.append(&collection);
+    content.append(&pane);
+
+    let restore = gtk::Button::builder()
+        .label("Restore")
+        .css_classes(["destructive-action"])
+        .sensitive(false)
+        .build();
+    let header = gtk::HeaderBar::new();
+    header.pack_end(&restore);
+    let window = gtk::Window::builder()
+        .title("Restore from Backup")
+        .transient_for(app_window)
+        .modal(true)
+        .default_width(640)
+        .default_height(420)
+        .titlebar(&header)
+        .child(&content)
+        .build();
+
+    let selected = Rc::new(RefCell::new(projects_files[0].clone()));
+    let backups: Rc<RefCell<Vec<PathBuf>>> = Rc::default();
+    refresh_backups(&list, &backups, &projects_files[0].1);
+    collection.connect_selected_notify(glib::clone!(
+        @weak list,
+        @strong backups,
+        @strong selected => move |collection| {
+            if let Some((name, path)) = projects_files.get(collection.selected() as usize) {
+                selected.replace((name.clone(), path.clone()));
+                refresh_backups(&list, &backups, path);
+            }
+        }
+    ));
+    list.connect_selected_rows_changed(glib::clone!(
+        @weak diff_view,
+        @weak restore,
+        @strong backups,
+        @strong selected => move |list| {
+            let backup_file = list
+                .selected_row()
+                .and_then(|row| backups.borrow().get(row.index() as usize).cloned());
+            restore.set_sensitive(backup_file.is_some());
+            let (name, projects_file) = &*selected.borrow();
+            let text = backup_file
+                .map(|backup_file| describe_diff(projects_file, name, &backup_file))
+                .unwrap_or_default();
+            diff_view.buffer().set_text(&text);
+        }
+    ));
+    restore.connect_clicked(glib::clone!(
+        @weak window,
+        @weak list,
+        @strong backups,
+        @strong selected => move |_| {
+            let backup_file = list
+                .selected_row()
+                .and_then(|row| backups.borrow().get(row.index() as usize).cloned());
+            let backup_file = match backup_file {
+                Some(backup_file) => backup_file,
+                None => return,
+            };
+            let (name, projects_file) = &*selected.borrow();
+            // The current file is backed up first, so restoring can be undone. Only this
+            // collection is restored, even from a database holding others. The file monitor
+            // reloads the projects afterwards.
+            let result = backup(projects_file)
+                .and_then(|_| config::load_projects(&backup_file, name))
+                .and_then(|projects| config::save_projects(projects_file, name, projects));
+            match result {
+                Ok(()) => window.close(),
+                Err(error) => show_error(&window, "Error restoring backup", &error),
+            }
+        }
+    ));
+    window.present();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project(name: &str, status: &str, tags: &[&str]) -> ProjectInfo {
+        toml::from_str(&format!(
+            "name = {:?}\nproject_type = \"rust\"\nstatus = {:?}\nlast_opened = 0\npath = \"/src/{}\"\ntags = {:?}",
+            name, status, name, tags
+        ))
+        .unwrap()
+    }
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("projlist-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn diff_keys() {
+        let current = vec![
+            project("kept", "active", &[]),
+            project("edited", "active", &["cli"]),
+            project("new", "active", &[]),
+        ];
+        let backup = vec![
+            project("kept", "active", &[]),
+            project("edited", "done", &[]),
+            project("deleted", "active", &[]),
+        ];
+        let diff = diff(&current, &backup);
+        assert_eq!(diff.added, ["deleted"]);
+        assert_eq!(diff.removed, ["new"]);
+        assert_eq!(
+            diff.changed,
+            [(
+                String::from("edited"),
+                vec![String::from("status"), String::from("tags")]
+            )]
+        );
+    }
+
+    #[test]
+    fn rotation() {
+        let dir = test_dir("rotation");
+        let projects_file = dir.join("projects.toml");
+        let backups = dir.join("backups");
+        for n in 0..MAX_BACKUPS + 5 {
+            std::fs::write(&projects_file, format!("# {}\n", n)).unwrap();
+            backup_into(&backups, &projects_file).unwrap();
+        }
+        let kept = list_backups_in(&backups, "toml");
+        // Unchanged contents aren't backed up again
+        backup_into(&backups, &projects_file).unwrap();
+        assert_eq!(list_backups_in(&backups, "toml"), kept);
+        assert_eq!(kept.len(), MAX_BACKUPS);
+        let newest = std::fs::read_to_string(&kept[0]).unwrap();
+        assert_eq!(newest, format!("# {}\n", MAX_BACKUPS + 4));
+        let oldest = std::fs::read_to_string(kept.last().unwrap()).unwrap();
+        assert_eq!(oldest, "# 5\n");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,197 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use gtk::{gdk, glib, prelude::*};
+use gtk_list_provider::ListProvider;
+
+use crate::{
+    open_project, project_changed, project_info::ProjectInfo, show_error, ProjectProvider,
+};
+
+const LANE_WIDTH: i32 = 200;
+
+fn build_card(provider: &ProjectProvider, item: &ProjectInfo) -> gtk::Widget {
+    let card = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(2)
+        .build();
+    let name = gtk::Label::builder().xalign(0.0).wrap(true).build();
+    name.set_markup(&format!(
+        "<b>{}</b>",
+        glib::markup_escape_text(&item.name())
+    ));
+    card.append(&name);
+    let details = gtk::Label::builder().xalign(0.0).wrap(true).build();
+    let mut text = item.project_type();
+    let tags = item.tags();
+    if !tags.is_empty() {
+        text.push_str(" · ");
+        text.push_str(&tags.join(", "));
+    }
+    details.set_markup(&format!(
+        "<small>{}</small>",
+        glib::markup_escape_text(&text)
+    ));
+    card.append(&details);
+    let frame = gtk::Frame::builder().child(&card).build();
+    card.set_margin_top(6);
+    card.set_margin_bottom(6);
+    card.set_margin_start(6);
+    card.set_margin_end(6);
+
+    let drag_source = gtk::DragSource::new();
+    drag_source.set_actions(gdk::DragAction::MOVE);
+    drag_source.set_content(Some(&gdk::ContentProvider::for_value(&item.to_value())));
+    frame.add_controller(drag_source);
+
+    let double_click = gtk::GestureClick::new();
+    double_click.connect_pressed(
+        glib::clone!(@strong provider, @strong item, @weak frame => move |_, n_press, _, _| {
+            if n_press == 2 {
+                if let Err(error) = open_project(&provider, &item) {
+                    show_error(&frame, "Error opening project", &error);
+                }
+            }
+        }),
+    );
+    frame.add_controller(double_click);
+    frame.upcast()
+}
+
+fn build_lane(provider: &ProjectProvider, status: &str, index: usize) -> gtk::Box {
+    let lane = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(6)
+        .width_request(LANE_WIDTH)
+        .build();
+    let status_class = format!("status-{}", index);
+    let header = gtk::Label::builder()
+        .label(status)
+        .halign(gtk::Align::Start)
+        .css_classes(["status-pill", status_class.as_str()])
+        .build();
+    lane.append(&header);
+
+    let drop_target = gtk::DropTarget::new(ProjectInfo::static_type(), gdk::DragAction::MOVE);
+    let status = status.to_owned();
+    drop_target.connect_drop(
+        glib::clone!(@strong provider, @weak lane => @default-return false, move |_, value, _, _| {
+            let item = match value.get::<ProjectInfo>() {
+                Ok(item) => item,
+                Err(_) => return false,
+            };
+            if item.status() == status {
+                return false;
+            }
+            // Respect the transitions allowed by the status config
+            let allowed = provider
+                .app_config
+                .borrow()
+                .next_statuses(&item.status())
+                .iter()
+                .any(|s| s.name == status);
+            if !allowed {
+                return false;
+            }
+            item.set_status(&status);
+            project_changed(&provider.model, &provider.unloaded.borrow(), &item, &lane);
+            true
+        }),
+    );
+    lane.add_controller(drop_target);
+    lane
+}
+
+/// Everything the board shows, to tell when it has to be rebuilt. Other changes, such as
+/// refreshed sizes and version control status, leave it alone so they don't cancel a drag.
+#[derive(PartialEq)]
+struct BoardState {
+    statuses: Vec<String>,
+    /// Each project with its lane, name, type and tags.
+    cards: Vec<(ProjectInfo, usize, String, String, Vec<String>)>,
+}
+
+fn board_state(provider: &ProjectProvider, model: &gtk::FilterListModel) -> BoardState {
+    let app_config = provider.app_config.borrow();
+    let cards = (0..model.n_items())
+        .filter_map(|position| model.item(position).and_downcast::<ProjectInfo>())
+        .map(|item| {
+            // Projects with an unknown status go in the first lane, matching the sort order
+            let lane = app_config.status_index(&item.status());
+            let (name, project_type, tags) = (item.name(), item.project_type(), item.tags());
+            (item, lane, name, project_type, tags)
+        })
+        .collect();
+    BoardState {
+        statuses: app_config
+            .statuses()
+            .iter()
+            .map(|s| s.name.clone())
+            .collect(),
+        cards,
+    }
+}
+
+fn refresh_board(board: &gtk::Box, provider: &ProjectProvider, state: &BoardState) {
+    while let Some(child) = board.first_child() {
+        board.remove(&child);
+    }
+    let lanes: Vec<gtk::Box> = state
+        .statuses
+        .iter()
+        .enumerate()
+        .map(|(index, status)| build_lane(provider, status, index))
+        .collect();
+    for lane in &lanes {
+        board.append(lane);
+    }
+    for (item, index, ..) in &state.cards {
+        if let Some(lane) = lanes.get(*index) {
+            lane.append(&build_card(provider, item));
+        }
+    }
+}
+
+/// Builds a board with a lane per status, showing the same projects as the list view.
+pub fn build_board(provider: &ProjectProvider) -> gtk::Widget {
+    let board = gtk::Box::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .spacing(12)
+        .margin_top(6)
+        .margin_bottom(6)
+        .margin_start(6)
+        .margin_end(6)
+        .build();
+    let model = provider.model();
+    let shown = Rc::new(RefCell::new(board_state(provider, &model)));
+    refresh_board(&board, provider, &shown.borrow());
+
+    // Checked once per batch of changes, and not while a drop is still being handled
+    let refresh_queued = Rc::new(Cell::new(false));
+    model.connect_items_changed(
+        glib::clone!(@weak board, @strong provider, @strong shown, @strong refresh_queued => move |model, _, _, _| {
+            if refresh_queued.replace(true) {
+                return;
+            }
+            glib::idle_add_local_once(
+                glib::clone!(@weak board, @weak model, @strong provider, @strong shown, @strong refresh_queued => move || {
+                    refresh_queued.set(false);
+                    let state = board_state(&provider, &model);
+                    if state != *shown.borrow() {
+                        refresh_board(&board, &provider, &state);
+                        shown.replace(state);
+                    }
+                }),
+            );
+        }),
+    );
+
+    gtk::ScrolledWindow::builder()
+        .hexpand(true)
+        .vexpand(true)
+        .child(&board)
+        .build()
+        .upcast()
+}
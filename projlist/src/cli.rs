@@ -0,0 +1,319 @@
+use std::{collections::BTreeMap, path::Path};
+
+use gtk::glib;
+use serde::Serialize;
+
+use crate::{
+    command,
+    config::{self, AppConfig, Collection, ProjectInfo, Session, StatusChange},
+    store,
+};
+
+const USAGE: &str = "Usage:
+  projlist [FOLDER]                         Show the project list, selecting or adding FOLDER
+  projlist --quick-open                     Show the quick open palette
+  projlist list [--status STATUS] [--type TYPE] [--tag TAG] [--collection NAME]
+                [--archived] [--format text|json]
+  projlist open NAME [--collection NAME]
+  projlist add PATH [--name NAME] [--type TYPE] [--status STATUS] [--collection NAME]
+  projlist set-status NAME STATUS [--collection NAME]
+  projlist migrate FROM TO [--collection NAME]
+                                            Copy a projects file to a new .toml, .json or .sqlite file";
+
+const SUBCOMMANDS: &[&str] = &["list", "open", "add", "set-status", "migrate", "help"];
+
+/// Options that don't take a value.
+const FLAGS: &[&str] = &["archived"];
+
+#[derive(Default)]
+struct Args {
+    positional: Vec<String>,
+    options: BTreeMap<String, String>,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Args::default();
+        while let Some(arg) = args.next() {
+            let option = match arg.strip_prefix("--") {
+                Some(option) => option,
+                None => {
+                    parsed.positional.push(arg);
+                    continue;
+                }
+            };
+            let (name, value) = match option.split_once('=') {
+                Some((name, value)) => (name.to_owned(), value.to_owned()),
+                None if FLAGS.contains(&option) => (option.to_owned(), String::new()),
+                None => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("Missing value for --{}", option))?;
+                    (option.to_owned(), value)
+                }
+            };
+            parsed.options.insert(name, value);
+        }
+        Ok(parsed)
+    }
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(String::as_str)
+    }
+    fn flag(&self, name: &str) -> bool {
+        self.options.contains_key(name)
+    }
+    fn positional(&self, index: usize, name: &str) -> Result<&str, String> {
+        self.positional
+            .get(index)
+            .map(String::as_str)
+            .ok_or_else(|| format!("Missing {}\n\n{}", name, USAGE))
+    }
+}
+
+#[derive(Serialize)]
+struct ListedProject<'a> {
+    collection: &'a str,
+    #[serde(flatten)]
+    project: &'a ProjectInfo,
+}
+
+struct Collections<'a> {
+    loaded: Vec<(&'a Collection, Vec<ProjectInfo>)>,
+}
+
+impl<'a> Collections<'a> {
+    /// Loads every collection, printing errors for the ones that can't be read.
+    fn load(app_config: &'a AppConfig, only: Option<&str>) -> Self {
+        let mut loaded = Vec::new();
+        for collection in app_config.collections() {
+            if only.map_or(false, |only| only != collection.name) {
+                continue;
+            }
+            let projects_file = collection.projects_file();
+            match config::load_projects(&projects_file, &collection.name) {
+                Ok(projects) => loaded.push((collection, projects)),
+                Err(error) => eprintln!("{}", error),
+            }
+        }
+        Collections { loaded }
+    }
+    fn find(&mut self, name: &str) -> Result<(&'a Collection, &mut ProjectInfo), String> {
+        let mut found = self.loaded.iter_mut().flat_map(|(collection, projects)| {
+            let collection: &'a Collection = *collection;
+            projects
+                .iter_mut()
+                .filter(move |project| project.name == name)
+                .map(move |project| (collection, project))
+        });
+        let first = found
+            .next()
+            .ok_or_else(|| format!("No project named \"{}\"", name))?;
+        if found.next().is_some() {
+            return Err(format!(
+                "More than one project is named \"{}\", choose one with --collection",
+                name
+            ));
+        }
+        Ok(first)
+    }
+    fn save(self, collection: &Collection) -> Result<(), String> {
+        let projects = self
+            .loaded
+            .into_iter()
+            .find(|(c, _)| c.name == collection.name)
+            .map(|(_, projects)| projects)
+            .ok_or_else(|| format!("Collection \"{}\" was not loaded", collection.name))?;
+        config::save_projects(&collection.projects_file(), &collection.name, projects)
+    }
+}
+
+fn now() -> i64 {
+    glib::DateTime::now_utc().unwrap().to_unix()
+}
+
+fn list(app_config: &AppConfig, args: &Args) -> Result<(), String> {
+    let collections = Collections::load(app_config, args.option("collection"));
+    let listed: Vec<ListedProject> = collections
+        .loaded
+        .iter()
+        .flat_map(|(collection, projects)| {
+            projects.iter().map(|project| ListedProject {
+                collection: &collection.name,
+                project,
+            })
+        })
+        .filter(|listed| {
+            let project = listed.project;
+            args.option("status").map_or(true, |s| project.status == s)
+                && args
+                    .option("type")
+                    .map_or(true, |t| project.project_type == t)
+                && args
+                    .option("tag")
+                    .map_or(true, |t| project.tags.iter().any(|tag| tag == t))
+                && (args.flag("archived") || !project.archived)
+        })
+        .collect();
+    match args.option("format").unwrap_or("text") {
+        "text" => {
+            for listed in listed {
+                let project = listed.project;
+                println!(
+                    "{}\t{}\t{}\t{}",
+                    project.name, project.status, project.project_type, project.path
+                );
+            }
+        }
+        "json" => {
+            let json = serde_json::to_string_pretty(&listed).map_err(|error| error.to_string())?;
+            println!("{}", json);
+        }
+        format => return Err(format!("Unknown format \"{}\"", format)),
+    }
+    Ok(())
+}
+
+fn open(app_config: &AppConfig, args: &Args) -> Result<(), String> {
+    let name = args.positional(1, "project name")?;
+    let mut collections = Collections::load(app_config, args.option("collection"));
+    let (collection, project) = collections.find(name)?;
+    command::open_project(
+        app_config,
+        &project.project_type,
+        project.application.as_deref(),
+        &project.path,
+    )?;
+    // The launched process outlives this command and can't be watched, so only the open is recorded
+    let now = now();
+    project.last_opened = now;
+    project.session.push(Session {
+        start: now,
+        end: Some(now),
+    });
+    collections.save(collection)
+}
+
+fn add(app_config: &AppConfig, args: &Args) -> Result<(), String> {
+    let path = args.positional(1, "project path")?;
+    let path = std::fs::canonicalize(path).map_err(|error| format!("{}: {}", path, error))?;
+    let mut project = ProjectInfo::new(app_config, &path, now())?;
+    if let Some(name) = args.option("name") {
+        project.name = name.to_owned();
+    }
+    if let Some(project_type) = args.option("type") {
+        if !app_config.project_type_names().contains(&project_type) {
+            return Err(format!("Unknown project type \"{}\"", project_type));
+        }
+        project.project_type = project_type.to_owned();
+    }
+    if let Some(status) = args.option("status") {
+        if app_config.status(status).is_none() {
+            return Err(format!("Unknown status \"{}\"", status));
+        }
+        project.status = status.to_owned();
+        project.history[0].status = status.to_owned();
+    }
+    let collection = match args.option("collection") {
+        Some(collection) => app_config
+            .collections()
+            .iter()
+            .find(|c| c.name == collection)
+            .ok_or_else(|| format!("Unknown collection \"{}\"", collection))?,
+        None => &app_config.collections()[0],
+    };
+
+    let projects_file = collection.projects_file();
+    config::create_default_projects(&projects_file, &collection.name)?;
+    let mut projects = config::load_projects(&projects_file, &collection.name)?;
+    if projects.iter().any(|p| p.name == project.name) {
+        return Err(format!(
+            "A project named \"{}\" already exists in {}",
+            project.name, collection.name
+        ));
+    }
+    projects.push(project);
+    config::save_projects(&projects_file, &collection.name, projects)
+}
+
+fn set_status(app_config: &AppConfig, args: &Args) -> Result<(), String> {
+    let name = args.positional(1, "project name")?;
+    let status = args.positional(2, "status")?;
+    if app_config.status(status).is_none() {
+        return Err(format!("Unknown status \"{}\"", status));
+    }
+    let mut collections = Collections::load(app_config, args.option("collection"));
+    let (collection, project) = collections.find(name)?;
+    if project.status == status {
+        return Ok(());
+    }
+    let allowed = app_config
+        .next_statuses(&project.status)
+        .iter()
+        .any(|s| s.name == status);
+    if !allowed {
+        return Err(format!(
+            "Cannot change {} from {} to {}",
+            project.name, project.status, status
+        ));
+    }
+    project.status = status.to_owned();
+    project.history.push(StatusChange {
+        status: status.to_owned(),
+        time: now(),
+    });
+    collections.save(collection)
+}
+
+fn migrate(app_config: &AppConfig, args: &Args) -> Result<(), String> {
+    let from = args.positional(1, "projects file to migrate from")?;
+    let to = args.positional(2, "projects file to migrate to")?;
+    // Only databases hold more than one collection, so the name is only used with them
+    let collection = args
+        .option("collection")
+        .unwrap_or(&app_config.collections()[0].name);
+    let count = store::migrate(Path::new(from), Path::new(to), collection)?;
+    println!(
+        "Copied {} projects to {}. Change the collection's path to use it.",
+        count, to
+    );
+    Ok(())
+}
+
+/// Runs a subcommand, or returns None to start the GUI when there isn't one. Other arguments,
+/// such as options and folders to open, are left for GApplication.
+pub fn run(args: Vec<String>) -> Option<glib::ExitCode> {
+    let subcommand = args
+        .first()
+        .filter(|arg| SUBCOMMANDS.contains(&arg.as_str()))?
+        .clone();
+    let args = match Args::parse(args.into_iter()) {
+        Ok(args) => args,
+        Err(error) => {
+            eprintln!("{}", error);
+            return Some(glib::ExitCode::FAILURE);
+        }
+    };
+    let (app_config, errors) = crate::load_config();
+    for error in errors {
+        eprintln!("{}", error);
+    }
+    let result = match subcommand.as_str() {
+        "list" => list(&app_config, &args),
+        "open" => open(&app_config, &args),
+        "add" => add(&app_config, &args),
+        "set-status" => set_status(&app_config, &args),
+        "migrate" => migrate(&app_config, &args),
+        "help" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        subcommand => Err(format!("Unknown command \"{}\"\n\n{}", subcommand, USAGE)),
+    };
+    match result {
+        Ok(()) => Some(glib::ExitCode::SUCCESS),
+        Err(error) => {
+            eprintln!("{}", error);
+            Some(glib::ExitCode::FAILURE)
+        }
+    }
+}
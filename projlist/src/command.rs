@@ -0,0 +1,67 @@
+use std::ffi::OsStr;
+
+use gtk::{
+    gio::{self, DesktopAppInfo, SubprocessFlags, SubprocessLauncher},
+    glib,
+    prelude::*,
+};
+
+use crate::{
+    config::{AppConfig, ProjectCommand},
+    project_info::ProjectInfo,
+};
+
+fn expand_placeholders(arg: &str, item: &ProjectInfo) -> String {
+    arg.replace("{path}", &item.path())
+        .replace("{name}", &item.name())
+        .replace("{type}", &item.project_type())
+        .replace("{status}", &item.status())
+}
+
+pub fn run_command(command: &ProjectCommand, item: &ProjectInfo) -> Result<(), String> {
+    // Placeholders are expanded after splitting, so values containing spaces stay one argument.
+    let argv = gtk::glib::shell_parse_argv(&command.command)
+        .map_err(|error| format!("Invalid command \"{}\": {}", command.command, error))?;
+    let argv: Vec<String> = argv
+        .iter()
+        .map(|arg| expand_placeholders(&arg.to_string_lossy(), item))
+        .collect();
+    let argv: Vec<&OsStr> = argv.iter().map(OsStr::new).collect();
+    let launcher = SubprocessLauncher::new(SubprocessFlags::NONE);
+    launcher.set_cwd(item.path());
+    launcher
+        .spawn(&argv)
+        .map(|_| ())
+        .map_err(|error| format!("Error running \"{}\": {}", command.name, error))
+}
+
+/// Opens `path` with the project's own application, or its type's if it has none. Returns the
+/// launched process, which the caller must reap, unless the application was started over D-Bus.
+pub fn open_project(
+    app_config: &AppConfig,
+    project_type: &str,
+    application: Option<&str>,
+    path: &str,
+) -> Result<Option<glib::Pid>, String> {
+    let application = app_config
+        .project_application_id(project_type, application)
+        .and_then(DesktopAppInfo::new)
+        .ok_or_else(|| {
+            format!(
+                "Could not find application to open {} project.",
+                project_type
+            )
+        })?;
+    let uri = gio::File::for_path(path).uri();
+    let mut pid = None;
+    application
+        .launch_uris_as_manager(
+            &[&uri],
+            gio::AppLaunchContext::NONE,
+            glib::SpawnFlags::SEARCH_PATH | glib::SpawnFlags::DO_NOT_REAP_CHILD,
+            None,
+            Some(&mut |_, launched| pid = Some(launched)),
+        )
+        .map_err(|error| error.to_string())?;
+    Ok(pid)
+}
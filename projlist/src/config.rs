@@ -1,27 +1,294 @@
-use std::path::Path;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 
-use gtk::gio::DesktopAppInfo;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProjectCommand {
+    pub name: String,
+    pub command: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StatusChange {
+    pub status: String,
+    pub time: i64,
+}
+
+/// Time spent in a project, from opening it until it was closed or stopped.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub start: i64,
+    pub end: Option<i64>,
+}
+
+impl Session {
+    /// Seconds spent, counting a running session up to `now`.
+    pub fn duration(&self, now: i64) -> i64 {
+        self.end.unwrap_or(now) - self.start
+    }
+}
+
+/// What a link points at.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkTarget {
+    Project(String),
+    Url(String),
+    File(String),
+}
+
+/// A typed reference from a project to another project, a URL or a file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProjectLink {
+    /// How the target relates to the project, such as "uses" or "design".
+    pub kind: String,
+    #[serde(flatten)]
+    pub target: LinkTarget,
+}
+
+impl ProjectLink {
+    /// Parses "kind: target", or just a target for a "related" link. Targets are URLs when they
+    /// have a scheme, files when they look like a path, and project names otherwise.
+    pub fn parse(text: &str) -> Option<Self> {
+        let (kind, target) = match text.split_once(": ") {
+            Some((kind, target)) => (kind.trim(), target.trim()),
+            None => ("related", text.trim()),
+        };
+        if kind.is_empty() || target.is_empty() {
+            return None;
+        }
+        let target = if target.contains("://") {
+            LinkTarget::Url(target.to_owned())
+        } else if target.starts_with(['/', '~', '.']) {
+            LinkTarget::File(target.to_owned())
+        } else {
+            LinkTarget::Project(target.to_owned())
+        };
+        Some(ProjectLink {
+            kind: kind.to_owned(),
+            target,
+        })
+    }
+}
+
+#[derive(PartialEq, Serialize, Deserialize)]
 pub struct ProjectInfo {
     pub name: String,
     pub project_type: String,
     pub status: String,
     pub last_opened: i64,
     pub path: String,
+    #[serde(default)]
     pub notes: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(
+        default,
+        skip_serializing_if = "BTreeMap::is_empty",
+        deserialize_with = "deserialize_fields"
+    )]
+    pub fields: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub command: Vec<ProjectCommand>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<StatusChange>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub application: Option<String>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub archived: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub session: Vec<Session>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub link: Vec<ProjectLink>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+/// A custom field's value as written in the projects file.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FieldValue {
+    Text(String),
+    Date(toml::value::Datetime),
+    Other(toml::Value),
+}
+
+/// Reads custom fields written by hand as numbers, booleans or dates as their text, instead of
+/// failing to load the whole file.
+fn deserialize_fields<'de, D>(deserializer: D) -> Result<BTreeMap<String, String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let fields = BTreeMap::<String, FieldValue>::deserialize(deserializer)?;
+    Ok(fields
+        .into_iter()
+        .map(|(name, value)| {
+            let value = match value {
+                FieldValue::Text(text) => text,
+                FieldValue::Date(date) => date.to_string(),
+                FieldValue::Other(value) => value.to_string(),
+            };
+            (name, value)
+        })
+        .collect())
+}
+
+impl ProjectInfo {
+    /// A project for the folder at `path`, named after it, with the first configured type and
+    /// status.
+    pub fn new(app_config: &AppConfig, path: &Path, time: i64) -> Result<Self, String> {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .ok_or_else(|| format!("Cannot name a project after {}", path.display()))?;
+        let project_type = app_config
+            .project_types
+            .first()
+            .map(|t| t.name.clone())
+            .ok_or_else(|| String::from("No project types are configured"))?;
+        let status = app_config
+            .statuses
+            .first()
+            .map(|s| s.name.clone())
+            .ok_or_else(|| String::from("No statuses are configured"))?;
+        Ok(ProjectInfo {
+            name,
+            project_type,
+            status: status.clone(),
+            last_opened: 0,
+            path: path.to_string_lossy().into_owned(),
+            notes: String::new(),
+            tags: Vec::new(),
+            fields: BTreeMap::new(),
+            command: Vec::new(),
+            history: vec![StatusChange { status, time }],
+            application: None,
+            archived: false,
+            session: Vec::new(),
+            link: Vec::new(),
+        })
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Collection {
+    pub name: String,
+    pub path: String,
+    pub notes_dir: Option<String>,
+}
+
+pub fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(path) => gtk::glib::home_dir().join(path),
+        None => PathBuf::from(path),
+    }
+}
+
+impl Collection {
+    pub fn projects_file(&self) -> PathBuf {
+        expand_home(&self.path)
+    }
+    /// Notes go in `notes_dir` when set, otherwise in a `NOTES.md` in the project folder.
+    pub fn notes_file(&self, project_name: &str, project_path: &str) -> PathBuf {
+        match &self.notes_dir {
+            Some(notes_dir) => {
+                expand_home(notes_dir).join(format!("{}.md", project_name.replace('/', "-")))
+            }
+            None => Path::new(project_path).join("NOTES.md"),
+        }
+    }
 }
 
 #[derive(Deserialize)]
 struct ProjectType {
     name: String,
     application: String,
+    #[serde(default)]
+    command: Vec<ProjectCommand>,
+    template: Option<String>,
+    #[serde(default)]
+    post_create: Vec<String>,
+}
+
+/// A folder copied to create new projects of a type, and commands to run in the copy.
+pub struct Template {
+    pub path: PathBuf,
+    pub post_create: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Status {
+    pub name: String,
+    pub color: Option<String>,
+    pub icon: Option<String>,
+    pub transitions: Option<Vec<String>>,
+}
+
+/// Statuses can be plain names or tables with a color, icon and allowed transitions.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StatusEntry {
+    Name(String),
+    Status(Status),
+}
+
+impl From<StatusEntry> for Status {
+    fn from(entry: StatusEntry) -> Self {
+        match entry {
+            StatusEntry::Name(name) => Status {
+                name,
+                color: None,
+                icon: None,
+                transitions: None,
+            },
+            StatusEntry::Status(status) => status,
+        }
+    }
 }
 
 #[derive(Deserialize)]
 struct StatusesConfig {
-    status: Vec<String>,
+    status: Vec<StatusEntry>,
+}
+
+#[derive(Deserialize)]
+struct FieldsConfig {
+    field: Vec<String>,
+}
+
+/// Folders that hold generated files, which can be deleted and rebuilt.
+const DEFAULT_SIZE_IGNORE: &[&str] = &[
+    "target",
+    "node_modules",
+    "build",
+    "dist",
+    ".venv",
+    "venv",
+    "__pycache__",
+    ".gradle",
+    ".tox",
+    "zig-cache",
+];
+
+#[derive(Deserialize)]
+struct SizeConfig {
+    ignore: Vec<String>,
+}
+
+/// `vcs.toml`, for turning off reading version control status, which runs git for every project.
+#[derive(Deserialize)]
+struct VcsConfig {
+    enabled: bool,
+}
+
+#[derive(Deserialize)]
+struct CollectionsConfig {
+    collection: Vec<Collection>,
 }
 
 #[derive(Deserialize)]
@@ -30,22 +297,75 @@ struct TypesConfig {
 }
 
 #[derive(Serialize, Deserialize)]
-struct ProjectsConfig {
-    project: Vec<ProjectInfo>,
+pub struct ProjectsConfig {
+    #[serde(default)]
+    pub project: Vec<ProjectInfo>,
 }
 
 #[derive(Default)]
 pub struct AppConfig {
-    statuses: Vec<String>,
+    statuses: Vec<Status>,
     project_types: Vec<ProjectType>,
+    fields: Vec<String>,
+    collections: Vec<Collection>,
+    size_ignore: Vec<String>,
+    vcs_enabled: bool,
 }
 
 impl AppConfig {
-    pub fn statuses(&self) -> &[String] {
+    pub fn statuses(&self) -> &[Status] {
         &self.statuses
     }
+    pub fn status(&self, status: &str) -> Option<&Status> {
+        self.statuses.iter().find(|s| s.name == status)
+    }
+    /// Statuses a project can be moved to from `status`, including `status` itself.
+    pub fn next_statuses(&self, status: &str) -> Vec<&Status> {
+        match self.status(status).and_then(|s| s.transitions.as_ref()) {
+            Some(transitions) => self
+                .statuses
+                .iter()
+                .filter(|s| s.name == status || transitions.contains(&s.name))
+                .collect(),
+            None => self.statuses.iter().collect(),
+        }
+    }
+    pub fn collections(&self) -> &[Collection] {
+        &self.collections
+    }
+    pub fn fields(&self) -> &[String] {
+        &self.fields
+    }
+    /// Names of folders whose contents can be regenerated, which count as reclaimable space.
+    pub fn size_ignore(&self) -> &[String] {
+        &self.size_ignore
+    }
+    pub fn vcs_enabled(&self) -> bool {
+        self.vcs_enabled
+    }
     pub fn status_index(&self, status: &str) -> usize {
-        self.statuses.iter().position(|s| s == status).unwrap_or(0)
+        self.statuses
+            .iter()
+            .position(|s| s.name == status)
+            .unwrap_or(0)
+    }
+    pub fn project_type_names(&self) -> Vec<&str> {
+        self.project_types.iter().map(|t| t.name.as_str()).collect()
+    }
+    /// Names of the project types that can be created from a template.
+    pub fn template_type_names(&self) -> Vec<&str> {
+        self.project_types
+            .iter()
+            .filter(|t| t.template.is_some())
+            .map(|t| t.name.as_str())
+            .collect()
+    }
+    pub fn template(&self, project_type: &str) -> Option<Template> {
+        let project_type = self.project_types.iter().find(|t| t.name == project_type)?;
+        Some(Template {
+            path: expand_home(project_type.template.as_ref()?),
+            post_create: project_type.post_create.clone(),
+        })
     }
     pub fn project_type_index(&self, project_type: &str) -> usize {
         self.project_types
@@ -53,30 +373,225 @@ impl AppConfig {
             .position(|t| t.name == project_type)
             .unwrap_or(0)
     }
-    pub fn project_type_application(&self, project_type: &str) -> Option<DesktopAppInfo> {
-        self.project_types
+    /// The desktop file a project opens with: its own if set, otherwise its type's.
+    pub fn project_application_id<'a>(
+        &'a self,
+        project_type: &str,
+        application: Option<&'a str>,
+    ) -> Option<&'a str> {
+        application.or_else(|| {
+            self.project_types
+                .iter()
+                .find(|t| t.name == project_type)
+                .map(|t| t.application.as_str())
+        })
+    }
+    /// Commands for a project: its type's commands, with same-named project commands replacing
+    /// them and any others appended.
+    pub fn project_commands(
+        &self,
+        project_type: &str,
+        project_commands: &[ProjectCommand],
+    ) -> Vec<ProjectCommand> {
+        let mut commands: Vec<ProjectCommand> = self
+            .project_types
             .iter()
             .find(|t| t.name == project_type)
-            .and_then(|t| DesktopAppInfo::new(&t.application))
+            .map(|t| t.command.clone())
+            .unwrap_or_default();
+        for command in project_commands {
+            if let Some(existing) = commands.iter_mut().find(|c| c.name == command.name) {
+                *existing = command.clone();
+            } else {
+                commands.push(command.clone());
+            }
+        }
+        commands
     }
 }
 
-fn deserialize<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+const DEFAULT_STATUS_CONFIG: &str = include_str!("default_status.toml");
+const DEFAULT_TYPE_CONFIG: &str = include_str!("default_type.toml");
+
+fn line_column(contents: &str, offset: usize) -> (usize, usize) {
+    let before = contents.get(..offset).unwrap_or(contents);
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+pub fn deserialize<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
     let map_err_io = |error| format!("Error reading {}: {}", path.display(), error);
-    let map_err_toml = |error| format!("Error reading {}: {}", path.display(), error);
-    toml::from_str(&std::fs::read_to_string(path).map_err(map_err_io)?).map_err(map_err_toml)
+    let contents = std::fs::read_to_string(path).map_err(map_err_io)?;
+    let map_err_toml = |error: toml::de::Error| match error.span() {
+        Some(span) => {
+            let (line, column) = line_column(&contents, span.start);
+            format!(
+                "Error reading {} at line {}, column {}: {}",
+                path.display(),
+                line,
+                column,
+                error.message()
+            )
+        }
+        None => format!("Error reading {}: {}", path.display(), error.message()),
+    };
+    toml::from_str(&contents).map_err(map_err_toml)
+}
+
+pub fn serialize<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    let map_err_io = |error| format!("Error writing {}: {}", path.display(), error);
+    let map_err_toml = |error| format!("Error writing {}: {}", path.display(), error);
+    std::fs::write(path, toml::to_string_pretty(value).map_err(map_err_toml)?).map_err(map_err_io)
 }
 
-pub fn load_config(config_dir: &Path) -> Result<AppConfig, String> {
-    let statuses: StatusesConfig = deserialize(&config_dir.join("status.toml"))?;
-    let project_types: TypesConfig = deserialize(&config_dir.join("type.toml"))?;
-    Ok(AppConfig {
-        statuses: statuses.status,
-        project_types: project_types.r#type,
-    })
+fn write_default(path: &Path, contents: &str) -> Result<(), String> {
+    if path.exists() {
+        return Ok(());
+    }
+    let map_err_io = |error| format!("Error writing {}: {}", path.display(), error);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(map_err_io)?;
+    }
+    std::fs::write(path, contents).map_err(map_err_io)
+}
+
+pub fn create_default_config(config_dir: &Path) -> Result<(), String> {
+    write_default(&config_dir.join("status.toml"), DEFAULT_STATUS_CONFIG)?;
+    write_default(&config_dir.join("type.toml"), DEFAULT_TYPE_CONFIG)
+}
+
+/// Loads as much of the config as possible, returning an error for each file that failed.
+/// Without a `collection.toml`, projects are read from `default_collection`.
+pub fn load_config(config_dir: &Path, default_collection: Collection) -> (AppConfig, Vec<String>) {
+    let mut app_config = AppConfig::default();
+    let mut errors = Vec::new();
+    match deserialize::<StatusesConfig>(&config_dir.join("status.toml")) {
+        Ok(statuses) => {
+            app_config.statuses = statuses.status.into_iter().map(Status::from).collect()
+        }
+        Err(error) => errors.push(error),
+    }
+    match deserialize::<TypesConfig>(&config_dir.join("type.toml")) {
+        Ok(project_types) => app_config.project_types = project_types.r#type,
+        Err(error) => errors.push(error),
+    }
+    let fields_file = config_dir.join("field.toml");
+    if fields_file.exists() {
+        match deserialize::<FieldsConfig>(&fields_file) {
+            Ok(fields) => app_config.fields = fields.field,
+            Err(error) => errors.push(error),
+        }
+    }
+    app_config.size_ignore = DEFAULT_SIZE_IGNORE.iter().map(|s| s.to_string()).collect();
+    let size_file = config_dir.join("size.toml");
+    if size_file.exists() {
+        match deserialize::<SizeConfig>(&size_file) {
+            Ok(size) => app_config.size_ignore = size.ignore,
+            Err(error) => errors.push(error),
+        }
+    }
+    app_config.vcs_enabled = true;
+    let vcs_file = config_dir.join("vcs.toml");
+    if vcs_file.exists() {
+        match deserialize::<VcsConfig>(&vcs_file) {
+            Ok(vcs) => app_config.vcs_enabled = vcs.enabled,
+            Err(error) => errors.push(error),
+        }
+    }
+    let collections_file = config_dir.join("collection.toml");
+    if collections_file.exists() {
+        match deserialize::<CollectionsConfig>(&collections_file) {
+            Ok(collections) => app_config.collections = collections.collection,
+            Err(error) => errors.push(error),
+        }
+    }
+    if app_config.collections.is_empty() {
+        app_config.collections.push(default_collection);
+    }
+    (app_config, errors)
+}
+
+pub fn create_default_projects(projects_file: &Path, collection: &str) -> Result<(), String> {
+    if projects_file.exists() {
+        return Ok(());
+    }
+    save_projects(projects_file, collection, Vec::new())
+}
+
+/// Loads `collection`'s projects from `projects_file`, which can hold more than one collection
+/// if it's a database.
+pub fn load_projects(projects_file: &Path, collection: &str) -> Result<Vec<ProjectInfo>, String> {
+    crate::store::open(projects_file, collection).load()
 }
 
-pub fn load_projects(projects_file: &Path) -> Result<Vec<ProjectInfo>, String> {
-    let projects: ProjectsConfig = deserialize(projects_file)?;
-    Ok(projects.project)
+pub fn save_projects(
+    projects_file: &Path,
+    collection: &str,
+    projects: Vec<ProjectInfo>,
+) -> Result<(), String> {
+    crate::backup::backup(projects_file)?;
+    crate::store::open(projects_file, collection).save(projects)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(kind: &str, target: LinkTarget) -> Option<ProjectLink> {
+        Some(ProjectLink {
+            kind: kind.to_owned(),
+            target,
+        })
+    }
+
+    #[test]
+    fn link_kinds() {
+        assert_eq!(
+            ProjectLink::parse("uses: projlist"),
+            link("uses", LinkTarget::Project(String::from("projlist")))
+        );
+        assert_eq!(
+            ProjectLink::parse(" dirlist "),
+            link("related", LinkTarget::Project(String::from("dirlist")))
+        );
+        assert_eq!(
+            ProjectLink::parse("design: ~/Documents/design.md"),
+            link(
+                "design",
+                LinkTarget::File(String::from("~/Documents/design.md"))
+            )
+        );
+        assert_eq!(
+            ProjectLink::parse("./notes.md"),
+            link("related", LinkTarget::File(String::from("./notes.md")))
+        );
+    }
+
+    #[test]
+    fn link_urls() {
+        // The scheme's ":" isn't followed by a space, so it isn't taken for the kind
+        assert_eq!(
+            ProjectLink::parse("https://example.com/issues"),
+            link(
+                "related",
+                LinkTarget::Url(String::from("https://example.com/issues"))
+            )
+        );
+        assert_eq!(
+            ProjectLink::parse("ci: http://localhost:8080/job/build"),
+            link(
+                "ci",
+                LinkTarget::Url(String::from("http://localhost:8080/job/build"))
+            )
+        );
+    }
+
+    #[test]
+    fn empty_links() {
+        assert_eq!(ProjectLink::parse(""), None);
+        assert_eq!(ProjectLink::parse("   "), None);
+        assert_eq!(ProjectLink::parse(": projlist"), None);
+        assert_eq!(ProjectLink::parse("uses: "), None);
+    }
 }
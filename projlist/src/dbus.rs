@@ -0,0 +1,81 @@
+use gtk::{gio, glib, prelude::*};
+
+use crate::{open_project, project_info::ProjectInfo, ProjectProvider};
+
+/// The application ID with dashes replaced, as they aren't allowed in interface names.
+const INTERFACE_NAME: &str = "com.github.plish_plash.plash_gtk_apps.Projlist";
+const INTERFACE_XML: &str = r#"
+<node>
+  <interface name="com.github.plish_plash.plash_gtk_apps.Projlist">
+    <method name="OpenProject">
+      <arg type="s" name="name" direction="in"/>
+    </method>
+    <method name="ListProjects">
+      <arg type="as" name="names" direction="out"/>
+    </method>
+  </interface>
+</node>
+"#;
+
+fn handle_method_call(
+    provider: &ProjectProvider,
+    method_name: &str,
+    parameters: glib::Variant,
+    invocation: gio::DBusMethodInvocation,
+) {
+    let projects = (0..provider.model.n_items())
+        .filter_map(|position| provider.model.item(position).and_downcast::<ProjectInfo>());
+    match method_name {
+        "OpenProject" => {
+            let name = parameters.get::<(String,)>().map(|(name,)| name);
+            let item = projects
+                .filter(|item| !item.archived())
+                .find(|item| Some(item.name()) == name);
+            let result = match item {
+                Some(item) => open_project(provider, &item),
+                None => Err(String::from("No project with that name")),
+            };
+            match result {
+                Ok(()) => invocation.return_value(None),
+                Err(error) => invocation
+                    .return_dbus_error(&format!("{}.Error.Failed", INTERFACE_NAME), &error),
+            }
+        }
+        "ListProjects" => {
+            let names: Vec<String> = projects
+                .filter(|item| !item.archived())
+                .map(|item| item.name())
+                .collect();
+            invocation.return_value(Some(&(names,).to_variant()));
+        }
+        _ => invocation.return_dbus_error(
+            "org.freedesktop.DBus.Error.UnknownMethod",
+            &format!("Unknown method {}", method_name),
+        ),
+    }
+}
+
+/// Exports the projects interface next to the application's own actions.
+pub fn export(app: &gtk::Application, provider: &ProjectProvider) -> Result<(), String> {
+    let (connection, object_path) = match (app.dbus_connection(), app.dbus_object_path()) {
+        (Some(connection), Some(object_path)) => (connection, object_path),
+        _ => return Ok(()),
+    };
+    let interface_info = gio::DBusNodeInfo::for_xml(INTERFACE_XML)
+        .ok()
+        .and_then(|node_info| node_info.lookup_interface(INTERFACE_NAME))
+        .expect("invalid D-Bus interface XML");
+    connection
+        .register_object(
+            &object_path,
+            &interface_info,
+            glib::clone!(@strong provider => move |_, _, _, _, method_name, parameters, invocation| {
+                handle_method_call(&provider, method_name, parameters, invocation);
+            }),
+            // The interface has no properties, so GDBus rejects property calls before these run
+            |_, _, _, _, _| ().to_variant(),
+            |_, _, _, _, _, _| false,
+        )
+        .map(|_| ())
+        .map_err(|error| format!("Error exporting D-Bus interface: {}", error))
+}
@@ -0,0 +1,59 @@
+use std::path::Path;
+
+use gtk::{gio, gio::DesktopAppInfo, prelude::*};
+
+use crate::{config::AppConfig, project_info::ProjectInfo};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Problem {
+    MissingPath,
+    /// The desktop file id that could not be found, or None if the project type has no application.
+    MissingApplication(Option<String>),
+}
+
+impl Problem {
+    pub fn describe(&self, item: &ProjectInfo) -> String {
+        match self {
+            Problem::MissingPath => format!("The folder {} does not exist.", item.short_path()),
+            Problem::MissingApplication(Some(application)) => {
+                format!("The application {} is not installed.", application)
+            }
+            Problem::MissingApplication(None) => format!(
+                "There is no application for {} projects.",
+                item.project_type()
+            ),
+        }
+    }
+}
+
+/// Archived projects are never opened, so they aren't checked.
+pub fn check(item: &ProjectInfo, app_config: &AppConfig) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    if item.archived() {
+        return problems;
+    }
+    if !Path::new(&item.path()).is_dir() {
+        problems.push(Problem::MissingPath);
+    }
+    let application = item.application();
+    match app_config.project_application_id(&item.project_type(), application.as_deref()) {
+        Some(id) if DesktopAppInfo::new(id).is_none() => {
+            problems.push(Problem::MissingApplication(Some(id.to_owned())))
+        }
+        Some(_) => {}
+        None => problems.push(Problem::MissingApplication(None)),
+    }
+    problems
+}
+
+/// Re-checks every project, refreshing the rows whose problems changed.
+pub fn validate(model: &gio::ListStore, app_config: &AppConfig) {
+    for position in 0..model.n_items() {
+        let item = model.item(position).and_downcast::<ProjectInfo>().unwrap();
+        let problems = check(&item, app_config);
+        if item.problems() != problems {
+            item.set_problems(problems);
+            model.items_changed(position, 1, 1);
+        }
+    }
+}
@@ -1,27 +1,79 @@
+mod backup;
+mod board;
+mod cli;
+mod command;
 mod config;
+mod dbus;
+mod health;
+mod notes;
+mod palette;
 mod project_info;
+mod size;
+mod stats;
+mod store;
+mod template;
+mod timesheet;
+mod vcs;
 
-use std::{fmt, rc::Rc, time::Duration};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
 
-use config::AppConfig;
-use gtk::{gio::ListStore, glib, prelude::*};
+use config::{AppConfig, Collection, LinkTarget};
+use gtk::{gio, gio::ListStore, glib, prelude::*};
 use gtk_list_provider::*;
 
-use once_cell::unsync::OnceCell;
 use project_info::{ProjectInfo, ProjectInfoInner};
 
 const APP_ID: &str = "com.github.plish-plash.plash-gtk-apps.Projlist";
 const APP_CONFIG_DIR: &str = "projlist";
 const APP_PROJECTS_FILE: &str = "Projects.toml";
+const VCS_REFRESH_INTERVAL: u32 = 60;
+const SIZE_REFRESH_INTERVAL: u32 = 60 * 60;
+const NOTES_AUTOSAVE_DELAY: u32 = 2;
+/// Sessions left running by an earlier run are ended at most this long after they started.
+const SESSION_MAX_DURATION: i64 = 8 * 60 * 60;
+const STATUS_PILL_CSS: &str = ".status-pill { border-radius: 999px; padding: 0 8px; }\n";
+
+fn format_time_ago(dt: &glib::DateTime) -> String {
+    let formatter = timeago::Formatter::new();
+    let now = glib::DateTime::now(&dt.timezone()).unwrap();
+    formatter.convert(Duration::from_micros(
+        now.difference(dt).as_microseconds() as u64
+    ))
+}
 
 struct NameColumn;
 
 impl NameColumn {
-    fn bind_content(widget: gtk::Label, item: ProjectInfo) {
-        widget.set_text(item.name());
+    fn setup_content() -> gtk::Widget {
+        let widget = gtk::Box::new(gtk::Orientation::Horizontal, 4);
+        let icon = gtk::Image::from_icon_name("dialog-warning-symbolic");
+        widget.append(&icon);
+        let label = gtk::Label::builder().xalign(0.0).build();
+        widget.append(&label);
+        widget.upcast()
+    }
+    fn bind_content(widget: gtk::Widget, item: ProjectInfo) {
+        let icon: gtk::Image = widget.first_child().and_downcast().unwrap();
+        let problems: Vec<String> = item
+            .problems()
+            .iter()
+            .map(|problem| problem.describe(&item))
+            .collect();
+        icon.set_visible(!problems.is_empty());
+        icon.set_tooltip_text(Some(&problems.join("\n")));
+        let label: gtk::Label = icon.next_sibling().and_downcast().unwrap();
+        label.set_text(&item.name());
     }
     fn sort(a: &ProjectInfo, b: &ProjectInfo) -> gtk::Ordering {
-        a.name().cmp(b.name()).into()
+        a.name().cmp(&b.name()).into()
     }
 }
 
@@ -29,11 +81,11 @@ struct TypeColumn;
 
 impl TypeColumn {
     fn bind_content(widget: gtk::Label, item: ProjectInfo) {
-        widget.set_text(item.project_type());
+        widget.set_text(&item.project_type());
     }
     fn sort(app_config: &AppConfig, a: &ProjectInfo, b: &ProjectInfo) -> gtk::Ordering {
-        let a = app_config.project_type_index(a.project_type());
-        let b = app_config.project_type_index(b.project_type());
+        let a = app_config.project_type_index(&a.project_type());
+        let b = app_config.project_type_index(&b.project_type());
         a.cmp(&b).into()
     }
 }
@@ -41,12 +93,34 @@ impl TypeColumn {
 struct StatusColumn;
 
 impl StatusColumn {
-    fn bind_content(widget: gtk::Label, item: ProjectInfo) {
-        widget.set_text(item.status());
+    fn setup_content() -> gtk::Widget {
+        let widget = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(4)
+            .halign(gtk::Align::Start)
+            .css_classes(["status-pill"])
+            .build();
+        let icon = gtk::Image::new();
+        widget.append(&icon);
+        let label = gtk::Label::new(None);
+        widget.append(&label);
+        widget.upcast()
+    }
+    fn bind_content(app_config: &AppConfig, widget: gtk::Widget, item: ProjectInfo) {
+        let status = item.status();
+        let index = app_config.status_index(&status);
+        let status_class = format!("status-{}", index);
+        widget.set_css_classes(&["status-pill", status_class.as_str()]);
+        let icon: gtk::Image = widget.first_child().and_downcast().unwrap();
+        let icon_name = app_config.status(&status).and_then(|s| s.icon.clone());
+        icon.set_icon_name(icon_name.as_deref());
+        icon.set_visible(icon_name.is_some());
+        let label: gtk::Label = icon.next_sibling().and_downcast().unwrap();
+        label.set_text(&status);
     }
     fn sort(app_config: &AppConfig, a: &ProjectInfo, b: &ProjectInfo) -> gtk::Ordering {
-        let a = app_config.status_index(a.status());
-        let b = app_config.status_index(b.status());
+        let a = app_config.status_index(&a.status());
+        let b = app_config.status_index(&b.status());
         a.cmp(&b).into()
     }
 }
@@ -55,15 +129,9 @@ struct LastOpenedColumn;
 
 impl LastOpenedColumn {
     fn bind_content(widget: gtk::Label, item: ProjectInfo) {
-        let formatter = timeago::Formatter::new();
         let text = item
             .last_opened()
-            .map(|dt| {
-                let now = glib::DateTime::now(&dt.timezone()).unwrap();
-                formatter.convert(Duration::from_micros(
-                    now.difference(&dt).as_microseconds() as u64
-                ))
-            })
+            .map(|dt| format_time_ago(&dt))
             .unwrap_or_default();
         widget.set_text(&text);
     }
@@ -76,30 +144,217 @@ struct PathColumn;
 
 impl PathColumn {
     fn bind_content(widget: gtk::Label, item: ProjectInfo) {
-        widget.set_text(item.short_path());
+        widget.set_text(&item.short_path());
+    }
+    fn sort(a: &ProjectInfo, b: &ProjectInfo) -> gtk::Ordering {
+        a.path().cmp(&b.path()).into()
+    }
+}
+
+struct BranchColumn;
+
+impl BranchColumn {
+    fn bind_content(widget: gtk::Label, item: ProjectInfo) {
+        let text = item.vcs_status().map(|vcs| vcs.branch).unwrap_or_default();
+        widget.set_text(&text);
+    }
+    fn sort(a: &ProjectInfo, b: &ProjectInfo) -> gtk::Ordering {
+        let a = a.vcs_status().map(|vcs| vcs.branch);
+        let b = b.vcs_status().map(|vcs| vcs.branch);
+        a.cmp(&b).into()
+    }
+}
+
+struct ChangesColumn;
+
+impl ChangesColumn {
+    fn bind_content(widget: gtk::Label, item: ProjectInfo) {
+        let text = match item.vcs_status() {
+            Some(vcs) if vcs.dirty => "Dirty",
+            Some(_) => "Clean",
+            None => "",
+        };
+        widget.set_text(text);
+    }
+    fn sort(a: &ProjectInfo, b: &ProjectInfo) -> gtk::Ordering {
+        let a = a.vcs_status().map(|vcs| vcs.dirty);
+        let b = b.vcs_status().map(|vcs| vcs.dirty);
+        a.cmp(&b).into()
+    }
+}
+
+struct AheadBehindColumn;
+
+impl AheadBehindColumn {
+    fn bind_content(widget: gtk::Label, item: ProjectInfo) {
+        let text = item
+            .vcs_status()
+            .map(|vcs| vcs.ahead_behind())
+            .unwrap_or_default();
+        widget.set_text(&text);
+    }
+    fn sort(a: &ProjectInfo, b: &ProjectInfo) -> gtk::Ordering {
+        let a = a.vcs_status().map(|vcs| (vcs.ahead, vcs.behind));
+        let b = b.vcs_status().map(|vcs| (vcs.ahead, vcs.behind));
+        a.cmp(&b).into()
+    }
+}
+
+struct TagsColumn;
+
+impl TagsColumn {
+    fn bind_content(widget: gtk::Label, item: ProjectInfo) {
+        widget.set_text(&item.tags().join(", "));
+    }
+    fn sort(a: &ProjectInfo, b: &ProjectInfo) -> gtk::Ordering {
+        a.tags().cmp(&b.tags()).into()
+    }
+}
+
+struct NotesColumn;
+
+impl NotesColumn {
+    fn bind_content(widget: gtk::Label, item: ProjectInfo) {
+        widget.set_text(&item.notes_summary());
     }
     fn sort(a: &ProjectInfo, b: &ProjectInfo) -> gtk::Ordering {
-        a.path().cmp(b.path()).into()
+        a.notes_summary().cmp(&b.notes_summary()).into()
+    }
+}
+
+struct TotalTimeColumn;
+
+impl TotalTimeColumn {
+    fn bind_content(widget: gtk::Label, item: ProjectInfo) {
+        let total = timesheet::total_time(&item);
+        let text = if total > 0 {
+            timesheet::format_duration(total)
+        } else {
+            String::new()
+        };
+        widget.set_text(&text);
+    }
+    fn sort(a: &ProjectInfo, b: &ProjectInfo) -> gtk::Ordering {
+        timesheet::total_time(a)
+            .cmp(&timesheet::total_time(b))
+            .into()
+    }
+}
+
+struct RecentTimeColumn;
+
+impl RecentTimeColumn {
+    fn bind_content(widget: gtk::Label, item: ProjectInfo) {
+        let recent = timesheet::recent_time(&item);
+        let text = if recent > 0 {
+            timesheet::format_duration(recent)
+        } else {
+            String::new()
+        };
+        widget.set_text(&text);
+    }
+    fn sort(a: &ProjectInfo, b: &ProjectInfo) -> gtk::Ordering {
+        timesheet::recent_time(a)
+            .cmp(&timesheet::recent_time(b))
+            .into()
+    }
+}
+
+struct SizeColumn;
+
+impl SizeColumn {
+    fn bind_content(widget: gtk::Label, item: ProjectInfo) {
+        let text = item
+            .disk_usage()
+            .map(|usage| size::format_size(usage.size))
+            .unwrap_or_default();
+        widget.set_text(&text);
+    }
+    fn sort(a: &ProjectInfo, b: &ProjectInfo) -> gtk::Ordering {
+        let a = a.disk_usage().map(|usage| usage.size);
+        let b = b.disk_usage().map(|usage| usage.size);
+        a.cmp(&b).into()
+    }
+}
+
+struct ReclaimableColumn;
+
+impl ReclaimableColumn {
+    fn bind_content(widget: gtk::Label, item: ProjectInfo) {
+        let text = match item.disk_usage() {
+            Some(usage) if usage.reclaimable > 0 => size::format_size(usage.reclaimable),
+            _ => String::new(),
+        };
+        widget.set_text(&text);
+    }
+    fn sort(a: &ProjectInfo, b: &ProjectInfo) -> gtk::Ordering {
+        let a = a.disk_usage().map(|usage| usage.reclaimable);
+        let b = b.disk_usage().map(|usage| usage.reclaimable);
+        a.cmp(&b).into()
+    }
+}
+
+struct FieldColumn;
+
+impl FieldColumn {
+    fn bind_content(widget: gtk::Label, item: ProjectInfo, field: &str) {
+        widget.set_text(&item.field(field).unwrap_or_default());
+    }
+    fn sort(field: &str, a: &ProjectInfo, b: &ProjectInfo) -> gtk::Ordering {
+        a.field(field).cmp(&b.field(field)).into()
+    }
+}
+
+struct CollectionColumn;
+
+impl CollectionColumn {
+    fn bind_content(widget: gtk::Label, item: ProjectInfo) {
+        widget.set_text(&item.collection());
+    }
+    fn sort(a: &ProjectInfo, b: &ProjectInfo) -> gtk::Ordering {
+        a.collection().cmp(&b.collection()).into()
     }
 }
 
 #[derive(Clone)]
 enum ProjectColumn {
     Name,
-    Type(Rc<OnceCell<AppConfig>>),
-    Status(Rc<OnceCell<AppConfig>>),
+    Type(Rc<RefCell<AppConfig>>),
+    Status(Rc<RefCell<AppConfig>>),
     LastOpened,
     Path,
+    Branch,
+    Changes,
+    AheadBehind,
+    Tags,
+    Notes,
+    TotalTime,
+    RecentTime,
+    Size,
+    Reclaimable,
+    Field(String),
+    Collection,
 }
 
 impl fmt::Display for ProjectColumn {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
+        match self {
             ProjectColumn::Name => write!(f, "Name"),
             ProjectColumn::Type(_) => write!(f, "Type"),
             ProjectColumn::Status(_) => write!(f, "Status"),
             ProjectColumn::LastOpened => write!(f, "Last Opened"),
             ProjectColumn::Path => write!(f, "Path"),
+            ProjectColumn::Branch => write!(f, "Branch"),
+            ProjectColumn::Changes => write!(f, "Changes"),
+            ProjectColumn::AheadBehind => write!(f, "Ahead/Behind"),
+            ProjectColumn::Tags => write!(f, "Tags"),
+            ProjectColumn::Notes => write!(f, "Notes"),
+            ProjectColumn::TotalTime => write!(f, "Total Time"),
+            ProjectColumn::RecentTime => write!(f, "Last {} Days", timesheet::RECENT_DAYS),
+            ProjectColumn::Size => write!(f, "Size"),
+            ProjectColumn::Reclaimable => write!(f, "Reclaimable"),
+            ProjectColumn::Field(field) => write!(f, "{}", field),
+            ProjectColumn::Collection => write!(f, "Collection"),
         }
     }
 }
@@ -107,16 +362,34 @@ impl fmt::Display for ProjectColumn {
 impl ListContent for ProjectColumn {
     type ModelItem = ProjectInfo;
     fn setup_content(&self) -> gtk::Widget {
-        gtk::Label::builder().xalign(0.0).build().upcast()
+        match self {
+            ProjectColumn::Name => NameColumn::setup_content(),
+            ProjectColumn::Status(_) => StatusColumn::setup_content(),
+            _ => gtk::Label::builder().xalign(0.0).build().upcast(),
+        }
     }
     fn bind_content(&self, widget: gtk::Widget, item: Self::ModelItem) {
-        let widget: gtk::Label = widget.downcast().unwrap();
+        // Every column but the name and status shows a label
+        let label = |widget: gtk::Widget| widget.downcast::<gtk::Label>().unwrap();
         match self {
             ProjectColumn::Name => NameColumn::bind_content(widget, item),
-            ProjectColumn::Type(_) => TypeColumn::bind_content(widget, item),
-            ProjectColumn::Status(_) => StatusColumn::bind_content(widget, item),
-            ProjectColumn::LastOpened => LastOpenedColumn::bind_content(widget, item),
-            ProjectColumn::Path => PathColumn::bind_content(widget, item),
+            ProjectColumn::Status(app_config) => {
+                StatusColumn::bind_content(&app_config.borrow(), widget, item)
+            }
+            ProjectColumn::Type(_) => TypeColumn::bind_content(label(widget), item),
+            ProjectColumn::LastOpened => LastOpenedColumn::bind_content(label(widget), item),
+            ProjectColumn::Path => PathColumn::bind_content(label(widget), item),
+            ProjectColumn::Branch => BranchColumn::bind_content(label(widget), item),
+            ProjectColumn::Changes => ChangesColumn::bind_content(label(widget), item),
+            ProjectColumn::AheadBehind => AheadBehindColumn::bind_content(label(widget), item),
+            ProjectColumn::Tags => TagsColumn::bind_content(label(widget), item),
+            ProjectColumn::Notes => NotesColumn::bind_content(label(widget), item),
+            ProjectColumn::TotalTime => TotalTimeColumn::bind_content(label(widget), item),
+            ProjectColumn::RecentTime => RecentTimeColumn::bind_content(label(widget), item),
+            ProjectColumn::Size => SizeColumn::bind_content(label(widget), item),
+            ProjectColumn::Reclaimable => ReclaimableColumn::bind_content(label(widget), item),
+            ProjectColumn::Field(field) => FieldColumn::bind_content(label(widget), item, field),
+            ProjectColumn::Collection => CollectionColumn::bind_content(label(widget), item),
         }
     }
 }
@@ -125,17 +398,452 @@ impl ListColumn for ProjectColumn {
     fn sort(&self, a: &Self::ModelItem, b: &Self::ModelItem) -> gtk::Ordering {
         match self {
             ProjectColumn::Name => NameColumn::sort(a, b),
-            ProjectColumn::Type(app_config) => TypeColumn::sort(app_config.get().unwrap(), a, b),
-            ProjectColumn::Status(app_config) => {
-                StatusColumn::sort(app_config.get().unwrap(), a, b)
-            }
+            ProjectColumn::Type(app_config) => TypeColumn::sort(&app_config.borrow(), a, b),
+            ProjectColumn::Status(app_config) => StatusColumn::sort(&app_config.borrow(), a, b),
             ProjectColumn::LastOpened => LastOpenedColumn::sort(a, b),
             ProjectColumn::Path => PathColumn::sort(a, b),
+            ProjectColumn::Branch => BranchColumn::sort(a, b),
+            ProjectColumn::Changes => ChangesColumn::sort(a, b),
+            ProjectColumn::AheadBehind => AheadBehindColumn::sort(a, b),
+            ProjectColumn::Tags => TagsColumn::sort(a, b),
+            ProjectColumn::Notes => NotesColumn::sort(a, b),
+            ProjectColumn::TotalTime => TotalTimeColumn::sort(a, b),
+            ProjectColumn::RecentTime => RecentTimeColumn::sort(a, b),
+            ProjectColumn::Size => SizeColumn::sort(a, b),
+            ProjectColumn::Reclaimable => ReclaimableColumn::sort(a, b),
+            ProjectColumn::Field(field) => FieldColumn::sort(field, a, b),
+            ProjectColumn::Collection => CollectionColumn::sort(a, b),
+        }
+    }
+    fn context_menu(&self, item: &Self::ModelItem) -> Option<gio::MenuModel> {
+        if item.commands().is_empty() {
+            return None;
         }
+        let menu = gio::Menu::new();
+        for command in item.commands() {
+            let menu_item = gio::MenuItem::new(Some(&command.name), None);
+            menu_item.set_action_and_target_value(
+                Some("win.run-command"),
+                Some(&command.name.to_variant()),
+            );
+            menu.append_item(&menu_item);
+        }
+        Some(menu.upcast())
+    }
+}
+
+struct ProjectDetail {
+    app_config: Rc<RefCell<AppConfig>>,
+    model: ListStore,
+    unloaded: Rc<RefCell<BTreeSet<String>>>,
+    item: Rc<RefCell<Option<ProjectInfo>>>,
+    pending_notes: Rc<RefCell<Option<PendingNotes>>>,
+    applications: Rc<RefCell<Option<Vec<gio::AppInfo>>>>,
+    /// What the shown project was bound with, to tell saved changes from refreshed status.
+    bound: RefCell<Option<config::ProjectInfo>>,
+}
+
+/// Applications that can open projects, sorted by name. Listing them is slow, so it's done the
+/// first time they're needed and kept.
+fn installed_applications(cache: &RefCell<Option<Vec<gio::AppInfo>>>) -> Vec<gio::AppInfo> {
+    cache
+        .borrow_mut()
+        .get_or_insert_with(|| {
+            let mut applications: Vec<gio::AppInfo> = gio::AppInfo::all()
+                .into_iter()
+                .filter(|application| application.should_show() && application.id().is_some())
+                .collect();
+            applications.sort_by_key(|application| application.display_name().to_lowercase());
+            applications
+        })
+        .clone()
+}
+
+/// Notes edited in the detail pane that autosave hasn't written yet.
+struct PendingNotes {
+    source: glib::SourceId,
+    item: ProjectInfo,
+    view: gtk::TextView,
+    /// Held apart from `view`, which can be disposed before the app shuts down.
+    buffer: gtk::TextBuffer,
+}
+
+fn save_notes(
+    model: &ListStore,
+    unloaded: &BTreeSet<String>,
+    item: &ProjectInfo,
+    buffer: &gtk::TextBuffer,
+    view: &gtk::TextView,
+) {
+    let (start, end) = buffer.bounds();
+    let notes = buffer.text(&start, &end, false);
+    if notes.as_str() == item.notes() {
+        return;
+    }
+    match item.set_notes(&notes) {
+        Ok(()) => project_changed(model, unloaded, item, view),
+        Err(error) => show_error(view, "Error saving notes", &error),
     }
 }
 
-struct ProjectDetail(Rc<OnceCell<AppConfig>>);
+/// Saves notes waiting for autosave right away.
+fn flush_notes(
+    model: &ListStore,
+    unloaded: &BTreeSet<String>,
+    pending_notes: &RefCell<Option<PendingNotes>>,
+) {
+    if let Some(PendingNotes {
+        source,
+        item,
+        view,
+        buffer,
+    }) = pending_notes.take()
+    {
+        source.remove();
+        save_notes(model, unloaded, &item, &buffer, &view);
+    }
+}
+
+/// Re-checks projects after `item` was changed to fix a problem, then saves it.
+fn problem_fixed(
+    app_config: &AppConfig,
+    model: &ListStore,
+    unloaded: &BTreeSet<String>,
+    item: &ProjectInfo,
+    widget: &impl IsA<gtk::Widget>,
+) {
+    health::validate(model, app_config);
+    project_changed(model, unloaded, item, widget);
+}
+
+/// Position of the project named `name` in `model`, preferring one in `collection`.
+fn find_project(model: &ListStore, name: &str, collection: &str) -> Option<u32> {
+    let matches: Vec<(u32, ProjectInfo)> = (0..model.n_items())
+        .filter_map(|position| {
+            let item = model.item(position).and_downcast::<ProjectInfo>()?;
+            (item.name() == name).then_some((position, item))
+        })
+        .collect();
+    matches
+        .iter()
+        .find(|(_, item)| item.collection() == collection)
+        .or_else(|| matches.first())
+        .map(|(position, _)| *position)
+}
+
+/// Projects linking to `item`, along with the kind of each link.
+fn backlinks(model: &ListStore, item: &ProjectInfo) -> Vec<(ProjectInfo, String)> {
+    let name = item.name();
+    (0..model.n_items())
+        .filter_map(|position| model.item(position).and_downcast::<ProjectInfo>())
+        .filter(|other| other != item)
+        .flat_map(|other| {
+            other
+                .links()
+                .into_iter()
+                .filter(|link| link.target == LinkTarget::Project(name.clone()))
+                .map(move |link| (other.clone(), link.kind))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Selects a linked project, or opens a linked URL or file in its default application. Relative
+/// files are found from the project folder.
+fn follow_link(
+    model: &ListStore,
+    item: &ProjectInfo,
+    target: &LinkTarget,
+    widget: &impl IsA<gtk::Widget>,
+) -> Result<(), String> {
+    let uri = match target {
+        LinkTarget::Project(name) => {
+            let position = find_project(model, name, &item.collection())
+                .ok_or_else(|| format!("No project named \"{}\"", name))?;
+            return widget
+                .activate_action("win.select-project", Some(&position.to_variant()))
+                .map_err(|error| error.to_string());
+        }
+        LinkTarget::Url(url) => url.clone(),
+        LinkTarget::File(file) => {
+            let path = Path::new(&item.path()).join(config::expand_home(file));
+            gio::File::for_path(path).uri().into()
+        }
+    };
+    gio::AppInfo::launch_default_for_uri(&uri, gio::AppLaunchContext::NONE)
+        .map_err(|error| format!("Error opening {}: {}", uri, error))
+}
+
+fn build_link_row(markup: &str, tooltip: &str) -> gtk::Button {
+    let label = gtk::Label::builder()
+        .xalign(0.0)
+        .ellipsize(gtk::pango::EllipsizeMode::Middle)
+        .build();
+    label.set_markup(markup);
+    gtk::Button::builder()
+        .child(&label)
+        .tooltip_text(tooltip)
+        .css_classes(["flat"])
+        .hexpand(true)
+        .build()
+}
+
+impl ProjectDetail {
+    fn bind_links(&self, link_box: &gtk::Box, item: &ProjectInfo) {
+        for link in item.links() {
+            let (target, tooltip) = match &link.target {
+                LinkTarget::Project(name) => {
+                    let missing = find_project(&self.model, name, &item.collection()).is_none();
+                    let tooltip = if missing {
+                        "Project not found"
+                    } else {
+                        "Select project"
+                    };
+                    (name.clone(), tooltip)
+                }
+                LinkTarget::Url(url) => (url.clone(), "Open URL"),
+                LinkTarget::File(file) => (file.clone(), "Open file"),
+            };
+            let row = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+            let button = build_link_row(
+                &format!(
+                    "<b>{}</b> → {}",
+                    glib::markup_escape_text(&link.kind),
+                    glib::markup_escape_text(&target)
+                ),
+                tooltip,
+            );
+            button.connect_clicked(
+                glib::clone!(@strong self.model as model, @strong item, @strong link => move |button| {
+                    if let Err(error) = follow_link(&model, &item, &link.target, button) {
+                        show_error(button, "Error following link", &error);
+                    }
+                }),
+            );
+            row.append(&button);
+            let remove = gtk::Button::builder()
+                .label("✕")
+                .tooltip_text("Remove link")
+                .css_classes(["flat"])
+                .build();
+            remove.connect_clicked(
+                glib::clone!(@strong self.model as model, @strong self.unloaded as unloaded, @strong item => move |button| {
+                    item.remove_link(&link);
+                    project_changed(&model, &unloaded.borrow(), &item, button);
+                }),
+            );
+            row.append(&remove);
+            link_box.append(&row);
+        }
+        for (other, kind) in backlinks(&self.model, item) {
+            let button = build_link_row(
+                &format!(
+                    "<b>{}</b> ← {}",
+                    glib::markup_escape_text(&kind),
+                    glib::markup_escape_text(&other.name())
+                ),
+                "Select project",
+            );
+            button.connect_clicked(
+                glib::clone!(@strong self.model as model, @strong other => move |button| {
+                    let target = LinkTarget::Project(other.name());
+                    if let Err(error) = follow_link(&model, &other, &target, button) {
+                        show_error(button, "Error following link", &error);
+                    }
+                }),
+            );
+            link_box.append(&button);
+        }
+    }
+    fn build_problem(&self, item: &ProjectInfo, problem: &health::Problem) -> gtk::Widget {
+        let row = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+        row.append(&gtk::Image::from_icon_name("dialog-warning-symbolic"));
+        let message = gtk::Label::builder()
+            .label(problem.describe(item))
+            .xalign(0.0)
+            .wrap(true)
+            .hexpand(true)
+            .build();
+        row.append(&message);
+        match problem {
+            health::Problem::MissingPath => {
+                let relocate = gtk::Button::with_label("Relocate…");
+                relocate.connect_clicked(glib::clone!(
+                    @strong self.app_config as app_config,
+                    @strong self.model as model,
+                    @strong self.unloaded as unloaded,
+                    @strong item => move |button| {
+                        let dialog = gtk::FileDialog::builder()
+                            .title("Relocate Project")
+                            .modal(true)
+                            .build();
+                        let window = button.root().and_downcast::<gtk::Window>();
+                        dialog.select_folder(
+                            window.as_ref(),
+                            gio::Cancellable::NONE,
+                            glib::clone!(@strong app_config, @strong model, @strong unloaded, @strong item, @weak button => move |result| {
+                                if let Some(path) = result.ok().and_then(|folder| folder.path()) {
+                                    item.set_path(&path.to_string_lossy());
+                                    problem_fixed(&app_config.borrow(), &model, &unloaded.borrow(), &item, &button);
+                                }
+                            }),
+                        );
+                    }
+                ));
+                row.append(&relocate);
+            }
+            health::Problem::MissingApplication(_) => {
+                row.append(&self.build_application_chooser(item));
+            }
+        }
+        row.upcast()
+    }
+    fn build_archive_button(&self, item: &ProjectInfo, archived: bool) -> gtk::Button {
+        let button = gtk::Button::builder()
+            .label(if archived {
+                "Archive Project"
+            } else {
+                "Unarchive Project"
+            })
+            .halign(gtk::Align::Start)
+            .build();
+        button.connect_clicked(glib::clone!(
+            @strong self.app_config as app_config,
+            @strong self.model as model,
+            @strong self.unloaded as unloaded,
+            @strong item => move |button| {
+                item.set_archived(archived);
+                problem_fixed(&app_config.borrow(), &model, &unloaded.borrow(), &item, button);
+            }
+        ));
+        button
+    }
+    fn bind_time(&self, time_box: &gtk::Box, item: &ProjectInfo) {
+        let sessions = item.sessions();
+        if sessions.is_empty() {
+            return;
+        }
+        let total = gtk::Label::builder().xalign(0.0).build();
+        total.set_markup(&format!(
+            "<b>Time</b> {} total, {} in the last {} days",
+            timesheet::format_duration(timesheet::total_time(item)),
+            timesheet::format_duration(timesheet::recent_time(item)),
+            timesheet::RECENT_DAYS
+        ));
+        time_box.append(&total);
+        let running: Vec<_> = sessions.iter().filter(|s| s.end.is_none()).collect();
+        if let Some(first) = running.first() {
+            let text = match running.len() {
+                1 => format!("Running since {}", timesheet::format_time(first.start)),
+                count => format!(
+                    "{} sessions running since {}",
+                    count,
+                    timesheet::format_time(first.start)
+                ),
+            };
+            let row = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+            let label = gtk::Label::builder()
+                .label(text)
+                .xalign(0.0)
+                .hexpand(true)
+                .build();
+            row.append(&label);
+            let stop = gtk::Button::with_label("Stop");
+            stop.connect_clicked(
+                glib::clone!(@strong self.model as model, @strong self.unloaded as unloaded, @strong item => move |button| {
+                    if item.end_running_sessions(None) {
+                        project_changed(&model, &unloaded.borrow(), &item, button);
+                    }
+                }),
+            );
+            row.append(&stop);
+            time_box.append(&row);
+        }
+        let now = glib::DateTime::now_utc().unwrap().to_unix();
+        for session in sessions.iter().rev().filter(|s| s.end.is_some()).take(5) {
+            let label = gtk::Label::builder().xalign(0.0).build();
+            label.set_markup(&format!(
+                "<small>{}, {}</small>",
+                timesheet::format_time(session.start),
+                timesheet::format_duration(session.duration(now))
+            ));
+            time_box.append(&label);
+        }
+    }
+    fn build_application_chooser(&self, item: &ProjectInfo) -> gtk::Widget {
+        let list = gtk::ListBox::new();
+        let scrolled = gtk::ScrolledWindow::builder()
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .min_content_height(240)
+            .child(&list)
+            .build();
+        let popover = gtk::Popover::builder().child(&scrolled).build();
+        popover.connect_show(
+            glib::clone!(@strong self.applications as applications, @weak list => move |_| {
+                if list.first_child().is_some() {
+                    return;
+                }
+                for application in installed_applications(&applications) {
+                    let label = gtk::Label::builder()
+                        .label(application.display_name())
+                        .xalign(0.0)
+                        .build();
+                    list.append(&label);
+                }
+            }),
+        );
+        list.connect_row_activated(glib::clone!(
+            @strong self.applications as applications,
+            @strong self.app_config as app_config,
+            @strong self.model as model,
+            @strong self.unloaded as unloaded,
+            @strong item,
+            @weak popover => move |list, row| {
+                let id = installed_applications(&applications)
+                    .get(row.index() as usize)
+                    .and_then(|application| application.id());
+                if let Some(id) = id {
+                    item.set_application(Some(id.into()));
+                    popover.popdown();
+                    problem_fixed(&app_config.borrow(), &model, &unloaded.borrow(), &item, list);
+                }
+            }
+        ));
+        gtk::MenuButton::builder()
+            .label("Choose Application")
+            .popover(&popover)
+            .build()
+            .upcast()
+    }
+    fn bind_vcs(&self, vcs_box: &gtk::Box, item: &ProjectInfo) {
+        while let Some(child) = vcs_box.first_child() {
+            vcs_box.remove(&child);
+        }
+        if let Some(vcs) = item.vcs_status() {
+            let branch = gtk::Label::builder().xalign(0.0).build();
+            branch.set_markup(&format!(
+                "<b>{}</b> {}",
+                gtk::glib::markup_escape_text(&vcs.branch),
+                vcs.ahead_behind()
+            ));
+            vcs_box.append(&branch);
+            for commit in vcs.recent_commits {
+                let time = glib::DateTime::from_unix_local(commit.time)
+                    .map(|dt| format_time_ago(&dt))
+                    .unwrap_or_default();
+                let label = gtk::Label::builder().xalign(0.0).wrap(true).build();
+                label.set_markup(&format!(
+                    "<tt>{}</tt> {}\n<small>{}, {}</small>",
+                    gtk::glib::markup_escape_text(&commit.hash),
+                    gtk::glib::markup_escape_text(&commit.summary),
+                    gtk::glib::markup_escape_text(&commit.author),
+                    time
+                ));
+                vcs_box.append(&label);
+            }
+        }
+    }
+}
 
 impl ListContent for ProjectDetail {
     type ModelItem = ProjectInfo;
@@ -143,137 +851,1315 @@ impl ListContent for ProjectDetail {
         let outer = gtk::Box::new(gtk::Orientation::Vertical, 6);
         let name = gtk::Label::new(None);
         outer.append(&name);
+        let problem_box = gtk::Box::new(gtk::Orientation::Vertical, 6);
+        outer.append(&problem_box);
         let button_box = gtk::Box::new(gtk::Orientation::Horizontal, 6);
         let open_project = gtk::Button::builder().label("Open Project").build();
         button_box.append(&open_project);
         let open_folder = gtk::Button::builder().label("Open Folder").build();
         button_box.append(&open_folder);
         outer.append(&button_box);
-        let status_strings: Vec<&str> = self
-            .0
-            .get()
-            .unwrap()
-            .statuses()
-            .iter()
-            .map(|s| -> &str { s })
-            .collect();
-        let status = gtk::DropDown::from_strings(&status_strings);
+        let command_box = gtk::FlowBox::builder()
+            .selection_mode(gtk::SelectionMode::None)
+            .column_spacing(6)
+            .row_spacing(6)
+            .build();
+        outer.append(&command_box);
+        let status = gtk::DropDown::from_strings(&[]);
+        status.connect_selected_notify(
+            glib::clone!(@strong self.model as model, @strong self.unloaded as unloaded, @strong self.item as item => move |status| {
+                let item = item.borrow().clone();
+                let selected = status.selected_item().and_downcast::<gtk::StringObject>();
+                if let (Some(item), Some(selected)) = (item, selected) {
+                    if selected.string().as_str() != item.status() {
+                        item.set_status(&selected.string());
+                        project_changed(&model, &unloaded.borrow(), &item, status);
+                    }
+                }
+            }),
+        );
         outer.append(&status);
-        let notes = gtk::TextView::builder().hexpand(true).vexpand(true).build();
+        let status_history = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        outer.append(&status_history);
+        let tag_box = gtk::FlowBox::builder()
+            .selection_mode(gtk::SelectionMode::None)
+            .column_spacing(6)
+            .row_spacing(6)
+            .build();
+        outer.append(&tag_box);
+        let tag_entry = gtk::Entry::builder().placeholder_text("Add tag").build();
+        tag_entry.connect_activate(
+            glib::clone!(@strong self.model as model, @strong self.unloaded as unloaded, @strong self.item as item => move |entry| {
+                let item = item.borrow().clone();
+                if let Some(item) = item {
+                    if item.add_tag(entry.text().trim()) {
+                        project_changed(&model, &unloaded.borrow(), &item, entry);
+                    }
+                }
+                entry.set_text("");
+            }),
+        );
+        outer.append(&tag_entry);
+        let fields = gtk::Grid::builder().column_spacing(6).build();
+        outer.append(&fields);
+        let link_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        outer.append(&link_box);
+        let link_entry = gtk::Entry::builder()
+            .placeholder_text("Add link, such as \"uses: other-project\"")
+            .build();
+        link_entry.connect_activate(
+            glib::clone!(@strong self.model as model, @strong self.unloaded as unloaded, @strong self.item as item => move |entry| {
+                let item = item.borrow().clone();
+                let link = config::ProjectLink::parse(&entry.text());
+                if let (Some(item), Some(link)) = (item, link) {
+                    if item.add_link(link) {
+                        project_changed(&model, &unloaded.borrow(), &item, entry);
+                    }
+                }
+                entry.set_text("");
+            }),
+        );
+        outer.append(&link_entry);
+        let time_box = gtk::Box::new(gtk::Orientation::Vertical, 2);
+        outer.append(&time_box);
+        let notes = gtk::TextView::builder()
+            .hexpand(true)
+            .vexpand(true)
+            .wrap_mode(gtk::WrapMode::WordChar)
+            .build();
+        notes::create_tags(&notes.buffer());
+        notes.buffer().connect_changed(glib::clone!(
+            @strong self.model as model,
+            @strong self.unloaded as unloaded,
+            @strong self.item as item,
+            @strong self.pending_notes as pending_notes,
+            @weak notes => move |buffer| {
+                notes::highlight(buffer);
+                let item = match item.borrow().clone() {
+                    Some(item) => item,
+                    None => return,
+                };
+                if let Some(pending) = pending_notes.take() {
+                    pending.source.remove();
+                }
+                let source = glib::timeout_add_seconds_local_once(
+                    NOTES_AUTOSAVE_DELAY,
+                    glib::clone!(@strong model, @strong unloaded, @strong pending_notes => move || {
+                        if let Some(PendingNotes { item, view, buffer, .. }) = pending_notes.take() {
+                            save_notes(&model, &unloaded.borrow(), &item, &buffer, &view);
+                        }
+                    }),
+                );
+                pending_notes.replace(Some(PendingNotes {
+                    source,
+                    item,
+                    view: notes,
+                    buffer: buffer.clone(),
+                }));
+            }
+        ));
         outer.append(&notes);
+        let vcs_box = gtk::Box::new(gtk::Orientation::Vertical, 6);
+        outer.append(&vcs_box);
         outer.upcast()
     }
     fn bind_content(&self, widget: gtk::Widget, item: Self::ModelItem) {
+        // Save notes still waiting for autosave before the editor shows another project
+        let notes: gtk::TextView = widget
+            .last_child()
+            .and_then(|vcs_box| vcs_box.prev_sibling())
+            .and_downcast()
+            .unwrap();
+        flush_notes(&self.model, &self.unloaded.borrow(), &self.pending_notes);
+        // Cleared while binding, so handlers ignore changes made here
+        self.item.replace(None);
         let name: gtk::Label = widget.first_child().unwrap().downcast().unwrap();
         name.set_markup(&format!(
             "<big>{}</big>",
-            gtk::glib::markup_escape_text(item.name())
+            gtk::glib::markup_escape_text(&item.name())
         ));
+
+        let problem_box: gtk::Box = name.next_sibling().and_downcast().unwrap();
+        while let Some(child) = problem_box.first_child() {
+            problem_box.remove(&child);
+        }
+        let problems = item.problems();
+        for problem in &problems {
+            problem_box.append(&self.build_problem(&item, problem));
+        }
+        let stale = stats::is_stale(&item, stats::STALE_DAYS);
+        if item.archived() {
+            let label = gtk::Label::builder()
+                .label("This project is archived.")
+                .xalign(0.0)
+                .build();
+            problem_box.append(&label);
+            problem_box.append(&self.build_archive_button(&item, false));
+        } else if !problems.is_empty() || stale {
+            if problems.is_empty() {
+                let label = gtk::Label::builder()
+                    .label(format!(
+                        "This project hasn't been opened in over {} days.",
+                        stats::STALE_DAYS
+                    ))
+                    .xalign(0.0)
+                    .wrap(true)
+                    .build();
+                problem_box.append(&label);
+            }
+            problem_box.append(&self.build_archive_button(&item, true));
+        }
+
+        let button_box = problem_box.next_sibling().unwrap();
+        let command_box: gtk::FlowBox = button_box.next_sibling().and_downcast().unwrap();
+        while let Some(child) = command_box.first_child() {
+            command_box.remove(&child);
+        }
+        for command in item.commands() {
+            let button = gtk::Button::with_label(&command.name);
+            button.connect_clicked(
+                glib::clone!(@strong command, @strong item => move |button| {
+                    if let Err(error) = command::run_command(&command, &item) {
+                        show_error(button, "Error running command", &error);
+                    }
+                }),
+            );
+            command_box.insert(&button, -1);
+        }
+
+        // Only offer the statuses this project can move to
+        let status: gtk::DropDown = command_box.next_sibling().and_downcast().unwrap();
+        let app_config = self.app_config.borrow();
+        let next_statuses: Vec<&str> = app_config
+            .next_statuses(&item.status())
+            .into_iter()
+            .map(|s| s.name.as_str())
+            .collect();
+        status.set_model(Some(&gtk::StringList::new(&next_statuses)));
+        let current = next_statuses.iter().position(|s| *s == item.status());
+        status.set_selected(current.map_or(gtk::INVALID_LIST_POSITION, |p| p as u32));
+
+        let status_history: gtk::Box = status.next_sibling().and_downcast().unwrap();
+        while let Some(child) = status_history.first_child() {
+            status_history.remove(&child);
+        }
+        for change in item.status_history().iter().rev() {
+            let time = glib::DateTime::from_unix_local(change.time)
+                .map(|dt| format_time_ago(&dt))
+                .unwrap_or_default();
+            let label = gtk::Label::builder().xalign(0.0).build();
+            label.set_markup(&format!(
+                "<small>{} {}</small>",
+                glib::markup_escape_text(&change.status),
+                time
+            ));
+            status_history.append(&label);
+        }
+
+        let tag_box: gtk::FlowBox = status_history.next_sibling().and_downcast().unwrap();
+        while let Some(child) = tag_box.first_child() {
+            tag_box.remove(&child);
+        }
+        for tag in item.tags() {
+            let button = gtk::Button::builder()
+                .label(format!("{} ✕", tag))
+                .tooltip_text("Remove tag")
+                .build();
+            button.connect_clicked(
+                glib::clone!(@strong self.model as model, @strong self.unloaded as unloaded, @strong item => move |button| {
+                    item.remove_tag(&tag);
+                    project_changed(&model, &unloaded.borrow(), &item, button);
+                }),
+            );
+            tag_box.insert(&button, -1);
+        }
+
+        let tag_entry = tag_box.next_sibling().unwrap();
+        let fields: gtk::Grid = tag_entry.next_sibling().and_downcast().unwrap();
+        while let Some(child) = fields.first_child() {
+            fields.remove(&child);
+        }
+        for (row, (key, value)) in item.fields().iter().enumerate() {
+            let key_label = gtk::Label::builder().xalign(0.0).build();
+            key_label.set_markup(&format!("<b>{}</b>", glib::markup_escape_text(key)));
+            let value_label = gtk::Label::builder().xalign(0.0).wrap(true).build();
+            value_label.set_text(value);
+            fields.attach(&key_label, 0, row as i32, 1, 1);
+            fields.attach(&value_label, 1, row as i32, 1, 1);
+        }
+
+        let link_box: gtk::Box = fields.next_sibling().and_downcast().unwrap();
+        while let Some(child) = link_box.first_child() {
+            link_box.remove(&child);
+        }
+        self.bind_links(&link_box, &item);
+
+        let link_entry = link_box.next_sibling().unwrap();
+        let time_box: gtk::Box = link_entry.next_sibling().and_downcast().unwrap();
+        while let Some(child) = time_box.first_child() {
+            time_box.remove(&child);
+        }
+        self.bind_time(&time_box, &item);
+
+        // Only replace the text when it differs, so refreshes don't move the cursor
+        let buffer = notes.buffer();
+        let (start, end) = buffer.bounds();
+        if buffer.text(&start, &end, false).as_str() != item.notes() {
+            buffer.set_text(&item.notes());
+        }
+
+        let vcs_box: gtk::Box = widget.last_child().unwrap().downcast().unwrap();
+        self.bind_vcs(&vcs_box, &item);
+
+        self.bound.replace(Some(item.to_config()));
+        self.item.replace(Some(item));
+    }
+    fn update_content(&self, widget: gtk::Widget, item: Self::ModelItem) {
+        // Refreshed version control status, sizes and such don't change what's saved, and
+        // rebinding everything would close open popups and save notes early
+        let saved_unchanged = self.item.borrow().as_ref() == Some(&item)
+            && self.bound.borrow().as_ref() == Some(&item.to_config());
+        if saved_unchanged {
+            let vcs_box: gtk::Box = widget.last_child().unwrap().downcast().unwrap();
+            self.bind_vcs(&vcs_box, &item);
+        } else {
+            self.bind_content(widget, item);
+        }
+    }
+}
+
+#[derive(Default)]
+struct ProjectFilter {
+    tag: Option<String>,
+    collection: Option<String>,
+    show_archived: bool,
+}
+
+impl ProjectFilter {
+    fn matches(&self, item: &ProjectInfo) -> bool {
+        let tag_matches = match &self.tag {
+            Some(tag) => item.has_tag(tag),
+            None => true,
+        };
+        let collection_matches = match &self.collection {
+            Some(collection) => item.collection() == *collection,
+            None => true,
+        };
+        tag_matches && collection_matches && (self.show_archived || !item.archived())
     }
 }
 
 #[derive(Clone)]
 struct ProjectProvider {
-    app_config: Rc<OnceCell<AppConfig>>,
+    app_config: Rc<RefCell<AppConfig>>,
     model: ListStore,
+    /// Collections that failed to load. They aren't saved, as that would replace them with only
+    /// the projects shown.
+    unloaded: Rc<RefCell<BTreeSet<String>>>,
+    /// Shared with the detail pane, so notes can be saved when the app closes.
+    pending_notes: Rc<RefCell<Option<PendingNotes>>>,
+    filter: gtk::CustomFilter,
+    filter_state: Rc<RefCell<ProjectFilter>>,
+    monitors: Rc<RefCell<Vec<gio::FileMonitor>>>,
+    status_css: gtk::CssProvider,
+}
+
+impl ProjectProvider {
+    fn new() -> Self {
+        let filter_state: Rc<RefCell<ProjectFilter>> = Rc::default();
+        let filter = gtk::CustomFilter::new(glib::clone!(@strong filter_state => move |object| {
+            let item = object.downcast_ref::<ProjectInfo>().unwrap();
+            filter_state.borrow().matches(item)
+        }));
+        ProjectProvider {
+            app_config: Rc::default(),
+            model: ListStore::new(ProjectInfo::static_type()),
+            unloaded: Rc::default(),
+            pending_notes: Rc::default(),
+            filter,
+            filter_state,
+            monitors: Rc::default(),
+            status_css: gtk::CssProvider::new(),
+        }
+    }
+    fn set_tag_filter(&self, tag: Option<String>) {
+        self.filter_state.borrow_mut().tag = tag;
+        self.filter.changed(gtk::FilterChange::Different);
+    }
+    fn set_show_archived(&self, show_archived: bool) {
+        self.filter_state.borrow_mut().show_archived = show_archived;
+        self.filter.changed(gtk::FilterChange::Different);
+    }
+    fn load_status_css(&self) {
+        let mut css = String::from(STATUS_PILL_CSS);
+        for (index, status) in self.app_config.borrow().statuses().iter().enumerate() {
+            if let Some(color) = &status.color {
+                css.push_str(&format!(
+                    ".status-{} {{ background-color: {}; color: white; }}\n",
+                    index, color
+                ));
+            }
+        }
+        self.status_css.load_from_data(&css);
+    }
+    fn set_collection_filter(&self, collection: Option<String>) {
+        self.filter_state.borrow_mut().collection = collection;
+        self.filter.changed(gtk::FilterChange::Different);
+    }
 }
 
 impl ListProvider for ProjectProvider {
-    type Model = ListStore;
+    type Model = gtk::FilterListModel;
     type ModelItem = ProjectInfo;
     type Column = ProjectColumn;
     type Detail = ProjectDetail;
     fn model(&self) -> Self::Model {
-        self.model.clone()
+        gtk::FilterListModel::new(Some(self.model.clone()), Some(self.filter.clone()))
     }
     fn columns(&self) -> Vec<Self::Column> {
-        vec![
+        let mut columns = vec![
             ProjectColumn::Name,
             ProjectColumn::Type(self.app_config.clone()),
             ProjectColumn::Status(self.app_config.clone()),
             ProjectColumn::LastOpened,
             ProjectColumn::Path,
-        ]
+            ProjectColumn::Branch,
+            ProjectColumn::Changes,
+            ProjectColumn::AheadBehind,
+            ProjectColumn::Tags,
+            ProjectColumn::Notes,
+            ProjectColumn::TotalTime,
+            ProjectColumn::RecentTime,
+            ProjectColumn::Size,
+            ProjectColumn::Reclaimable,
+        ];
+        let fields = self.app_config.borrow().fields().to_vec();
+        columns.extend(fields.into_iter().map(ProjectColumn::Field));
+        columns.push(ProjectColumn::Collection);
+        columns
     }
     fn detail(&self) -> Self::Detail {
-        ProjectDetail(self.app_config.clone())
+        ProjectDetail {
+            app_config: self.app_config.clone(),
+            model: self.model.clone(),
+            unloaded: self.unloaded.clone(),
+            item: Rc::default(),
+            pending_notes: self.pending_notes.clone(),
+            applications: Rc::default(),
+            bound: RefCell::default(),
+        }
     }
 }
 
-fn load_config() -> Result<AppConfig, String> {
+fn show_error(widget: &impl IsA<gtk::Widget>, message: &str, detail: &str) {
+    let alert = gtk::AlertDialog::builder()
+        .modal(true)
+        .message(message)
+        .detail(detail)
+        .build();
+    alert.show(widget.root().and_downcast_ref::<gtk::Window>());
+}
+
+fn config_dir() -> PathBuf {
     let mut config_dir = gtk::glib::user_config_dir();
     config_dir.push(APP_CONFIG_DIR);
-    config::load_config(&config_dir)
+    config_dir
+}
+
+fn load_config() -> (AppConfig, Vec<String>) {
+    let config_dir = config_dir();
+    let mut errors = Vec::new();
+    if let Err(error) = config::create_default_config(&config_dir) {
+        errors.push(error);
+    }
+    let (app_config, load_errors) = config::load_config(&config_dir, default_collection());
+    errors.extend(load_errors);
+    (app_config, errors)
 }
 
-fn load_projects(model: &ListStore) -> Result<(), String> {
-    let mut projects_file = gtk::glib::home_dir();
-    projects_file.push(APP_PROJECTS_FILE);
-    let projects = config::load_projects(&projects_file)?;
-    let projects: Vec<_> = projects
+fn default_collection() -> Collection {
+    Collection {
+        name: String::from("Projects"),
+        path: format!("~/{}", APP_PROJECTS_FILE),
+        notes_dir: None,
+    }
+}
+
+/// Loads every collection's projects file, merging into the existing items so reloading keeps the
+/// selection. Items of a collection that fails to load are left as they are, and its name is added
+/// to `unloaded`.
+fn load_projects(
+    app_config: &AppConfig,
+    model: &ListStore,
+    unloaded: &RefCell<BTreeSet<String>>,
+) -> Vec<String> {
+    let mut errors = Vec::new();
+    let mut loaded = Vec::new();
+    let mut unloaded = unloaded.borrow_mut();
+    unloaded.clear();
+    for collection in app_config.collections() {
+        let projects_file = collection.projects_file();
+        let projects = config::create_default_projects(&projects_file, &collection.name)
+            .and_then(|_| config::load_projects(&projects_file, &collection.name));
+        match projects {
+            Ok(projects) => loaded.push((collection, projects)),
+            Err(error) => {
+                errors.push(error);
+                unloaded.insert(collection.name.clone());
+            }
+        }
+    }
+
+    let mut position = 0;
+    while position < model.n_items() {
+        let item = model.item(position).and_downcast::<ProjectInfo>().unwrap();
+        let collection = item.collection();
+        match loaded.iter_mut().find(|(c, _)| c.name == collection) {
+            Some((collection, projects)) => {
+                let name = item.name();
+                if let Some(index) = projects.iter().position(|info| info.name == name) {
+                    let info = projects.remove(index);
+                    let changed = item.to_config() != info;
+                    item.update(ProjectInfoInner::from_config(info, collection, app_config));
+                    if changed {
+                        model.items_changed(position, 1, 1);
+                    }
+                    position += 1;
+                } else {
+                    model.remove(position);
+                }
+            }
+            None if app_config
+                .collections()
+                .iter()
+                .any(|c| c.name == collection) =>
+            {
+                position += 1;
+            }
+            None => model.remove(position),
+        }
+    }
+    let projects: Vec<_> = loaded
         .into_iter()
-        .map(ProjectInfoInner::from_config)
+        .flat_map(|(collection, projects)| {
+            projects
+                .into_iter()
+                .map(move |info| ProjectInfoInner::from_config(info, collection, app_config))
+        })
         .map(ProjectInfo::new)
         .collect();
-    model.remove_all();
     model.extend_from_slice(&projects);
-    Ok(())
+    health::validate(model, app_config);
+    errors
+}
+
+fn report_errors(app: &gtk::Application, message: &str, errors: Vec<String>) {
+    if errors.is_empty() {
+        return;
+    }
+    let errors = errors.join("\n\n");
+    match app.active_window() {
+        Some(window) => show_error(&window, message, &errors),
+        None => eprintln!("{}", errors),
+    }
+}
+
+fn reload_config(app: &gtk::Application, provider: &ProjectProvider) {
+    let (app_config, errors) = load_config();
+    provider.app_config.replace(app_config);
+    provider.load_status_css();
+    let mut errors = errors;
+    errors.extend(load_projects(
+        &provider.app_config.borrow(),
+        &provider.model,
+        &provider.unloaded,
+    ));
+    // Sort order and commands depend on the config, so refresh every row
+    let n_items = provider.model.n_items();
+    provider.model.items_changed(0, n_items, n_items);
+    watch_files(app, provider);
+    refresh_notes(&provider.model);
+    report_errors(app, "Error reloading configuration", errors);
+}
+
+fn reload_projects(app: &gtk::Application, provider: &ProjectProvider) {
+    let errors = load_projects(
+        &provider.app_config.borrow(),
+        &provider.model,
+        &provider.unloaded,
+    );
+    report_errors(app, "Error reloading projects", errors);
+    refresh_sizes(&provider.model, &provider.app_config.borrow());
+    refresh_notes(&provider.model);
+}
+
+fn watch_file(path: &Path, on_change: impl Fn() + 'static) -> Result<gio::FileMonitor, String> {
+    let monitor = gio::File::for_path(path)
+        .monitor_file(gio::FileMonitorFlags::NONE, gio::Cancellable::NONE)
+        .map_err(|error| format!("Error watching {}: {}", path.display(), error))?;
+    monitor.connect_changed(move |_, _, _, event| {
+        if matches!(
+            event,
+            gio::FileMonitorEvent::ChangesDoneHint | gio::FileMonitorEvent::Created
+        ) {
+            on_change();
+        }
+    });
+    Ok(monitor)
 }
 
-fn build_window(app: &gtk::Application, provider: &ProjectProvider) {
+fn watch_files(app: &gtk::Application, provider: &ProjectProvider) {
+    for monitor in provider.monitors.take() {
+        monitor.cancel();
+    }
+    let config_dir = config_dir();
+    let mut monitors = Vec::new();
+    for file in [
+        "status.toml",
+        "type.toml",
+        "field.toml",
+        "size.toml",
+        "vcs.toml",
+        "collection.toml",
+    ] {
+        monitors.push(watch_file(
+            &config_dir.join(file),
+            glib::clone!(@weak app, @strong provider => move || {
+                reload_config(&app, &provider);
+            }),
+        ));
+    }
+    for collection in provider.app_config.borrow().collections() {
+        monitors.push(watch_file(
+            &collection.projects_file(),
+            glib::clone!(@weak app, @strong provider => move || {
+                reload_projects(&app, &provider);
+            }),
+        ));
+    }
+    for monitor in monitors {
+        match monitor {
+            Ok(monitor) => provider.monitors.borrow_mut().push(monitor),
+            Err(error) => eprintln!("{}", error),
+        }
+    }
+}
+
+/// Writes the projects of `item`'s collection.
+fn save_projects(
+    model: &ListStore,
+    unloaded: &BTreeSet<String>,
+    item: &ProjectInfo,
+) -> Result<(), String> {
+    let collection = item.collection();
+    if unloaded.contains(&collection) {
+        return Err(format!(
+            "Collection \"{}\" failed to load, so it isn't saved until it loads again",
+            collection
+        ));
+    }
+    let projects: Vec<_> = (0..model.n_items())
+        .filter_map(|position| model.item(position).and_downcast::<ProjectInfo>())
+        .filter(|other| other.collection() == collection)
+        .map(|other| other.to_config())
+        .collect();
+    config::save_projects(&item.projects_file(), &collection, projects)
+}
+
+fn refresh_item(model: &ListStore, item: &ProjectInfo) {
+    for position in 0..model.n_items() {
+        if model.item(position).as_ref() == Some(item.upcast_ref::<glib::Object>()) {
+            model.items_changed(position, 1, 1);
+        }
+    }
+}
+
+/// Refreshes the rows showing `item` and writes the projects file, reporting errors on `widget`.
+fn project_changed(
+    model: &ListStore,
+    unloaded: &BTreeSet<String>,
+    item: &ProjectInfo,
+    widget: &impl IsA<gtk::Widget>,
+) {
+    refresh_item(model, item);
+    if let Err(error) = save_projects(model, unloaded, item) {
+        show_error(widget, "Error saving projects", &error);
+    }
+}
+
+fn all_tags(model: &ListStore) -> Vec<String> {
+    let tags: BTreeSet<String> = (0..model.n_items())
+        .filter_map(|position| model.item(position).and_downcast::<ProjectInfo>())
+        .flat_map(|item| item.tags())
+        .collect();
+    tags.into_iter().collect()
+}
+
+/// Replaces the strings shown by `dropdown`, keeping the selected string if it still exists.
+fn update_string_list(list: &gtk::StringList, dropdown: &gtk::DropDown, strings: Vec<String>) {
+    let current: Vec<String> = (0..list.n_items())
+        .filter_map(|position| list.string(position))
+        .map(String::from)
+        .collect();
+    if current == strings {
+        return;
+    }
+    let selected = list.string(dropdown.selected());
+    let strs: Vec<&str> = strings.iter().map(String::as_str).collect();
+    list.splice(0, list.n_items(), &strs);
+    let position = selected
+        .and_then(|selected| {
+            strings
+                .iter()
+                .position(|string| string.as_str() == selected.as_str())
+        })
+        .unwrap_or(0);
+    dropdown.set_selected(position as u32);
+}
+
+fn refresh_tag_list(tag_list: &gtk::StringList, tag_dropdown: &gtk::DropDown, model: &ListStore) {
+    let mut tags = vec![String::from("All Tags")];
+    tags.extend(all_tags(model));
+    update_string_list(tag_list, tag_dropdown, tags);
+}
+
+const ALL_COLLECTIONS: &str = "All Collections";
+
+fn refresh_collection_list(
+    collection_list: &gtk::StringList,
+    collection_dropdown: &gtk::DropDown,
+    app_config: &AppConfig,
+) {
+    let mut collections: Vec<String> = app_config
+        .collections()
+        .iter()
+        .map(|collection| collection.name.clone())
+        .collect();
+    collection_dropdown.set_visible(collections.len() > 1);
+    collections.push(String::from(ALL_COLLECTIONS));
+    update_string_list(collection_list, collection_dropdown, collections);
+}
+
+fn select_collection(
+    provider: &ProjectProvider,
+    view: &gtk::ColumnView,
+    collection_dropdown: &gtk::DropDown,
+) {
+    let collection = collection_dropdown
+        .selected_item()
+        .and_downcast::<gtk::StringObject>()
+        .map(|collection| String::from(collection.string()));
+    let merged = collection.is_none() || collection.as_deref() == Some(ALL_COLLECTIONS);
+    let columns = view.columns();
+    for position in 0..columns.n_items() {
+        let column = columns
+            .item(position)
+            .and_downcast::<gtk::ColumnViewColumn>();
+        if let Some(column) = column {
+            if column.title().map_or(false, |title| title == "Collection") {
+                column.set_visible(merged);
+            }
+        }
+    }
+    provider.set_collection_filter(if merged { None } else { collection });
+}
+
+/// Marks a background refresh as running until dropped. It's held by the closure receiving the
+/// results, so the refresh can run again however that closure ends.
+struct Running(&'static AtomicBool);
+
+impl Running {
+    /// Returns None if the refresh is already running.
+    fn start(flag: &'static AtomicBool) -> Option<Self> {
+        if flag.swap(true, Ordering::SeqCst) {
+            None
+        } else {
+            Some(Running(flag))
+        }
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+fn refresh_vcs(model: &ListStore, app_config: &AppConfig) {
+    static READING: AtomicBool = AtomicBool::new(false);
+    if !app_config.vcs_enabled() {
+        return;
+    }
+    let running = match Running::start(&READING) {
+        Some(running) => running,
+        None => return,
+    };
+    let paths: Vec<String> = (0..model.n_items())
+        .filter_map(|position| model.item(position).and_downcast::<ProjectInfo>())
+        .map(|item| item.path())
+        .collect();
+    let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+    std::thread::spawn(move || {
+        for path in paths {
+            let vcs_status = vcs::read_status(Path::new(&path));
+            if sender.send(Some((path, vcs_status))).is_err() {
+                return;
+            }
+        }
+        let _ = sender.send(None);
+    });
+    receiver.attach(
+        None,
+        glib::clone!(@weak model => @default-return glib::Continue(false), move |read| {
+            let _running = &running;
+            let (path, vcs_status) = match read {
+                Some(read) => read,
+                None => return glib::Continue(false),
+            };
+            for position in 0..model.n_items() {
+                let item = model.item(position).and_downcast::<ProjectInfo>().unwrap();
+                if item.path() == path {
+                    item.set_vcs_status(vcs_status.clone());
+                    model.items_changed(position, 1, 1);
+                }
+            }
+            glib::Continue(true)
+        }),
+    );
+}
+
+/// Reads the summary of each project's notes in the background, for the notes column.
+fn refresh_notes(model: &ListStore) {
+    static READING: AtomicBool = AtomicBool::new(false);
+    let running = match Running::start(&READING) {
+        Some(running) => running,
+        None => return,
+    };
+    let notes: Vec<(PathBuf, String)> = (0..model.n_items())
+        .filter_map(|position| model.item(position).and_downcast::<ProjectInfo>())
+        .map(|item| (item.notes_file(), item.inline_notes()))
+        .collect();
+    let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+    std::thread::spawn(move || {
+        for (notes_file, inline_notes) in notes {
+            let summary =
+                notes::read_summary(&notes_file).unwrap_or_else(|| notes::summary(&inline_notes));
+            if sender.send(Some((notes_file, summary))).is_err() {
+                return;
+            }
+        }
+        let _ = sender.send(None);
+    });
+    receiver.attach(
+        None,
+        glib::clone!(@weak model => @default-return glib::Continue(false), move |read| {
+            let _running = &running;
+            let (notes_file, summary) = match read {
+                Some(read) => read,
+                None => return glib::Continue(false),
+            };
+            for position in 0..model.n_items() {
+                let item = model.item(position).and_downcast::<ProjectInfo>().unwrap();
+                if item.notes_file() == notes_file && item.notes_summary() != summary {
+                    item.set_notes_summary(summary.clone());
+                    model.items_changed(position, 1, 1);
+                }
+            }
+            glib::Continue(true)
+        }),
+    );
+}
+
+/// Shows cached project sizes, then scans the folders whose sizes are missing or older than
+/// `size::MAX_AGE` in the background.
+fn refresh_sizes(model: &ListStore, app_config: &AppConfig) {
+    static SCANNING: AtomicBool = AtomicBool::new(false);
+    let running = match Running::start(&SCANNING) {
+        Some(running) => running,
+        None => return,
+    };
+    let mut cache = size::load_cache();
+    let now = glib::DateTime::now_utc().unwrap().to_unix();
+    let mut stale = Vec::new();
+    for position in 0..model.n_items() {
+        let item = model.item(position).and_downcast::<ProjectInfo>().unwrap();
+        let path = item.path();
+        let usage = cache.get(&path).copied();
+        if usage.map_or(true, |usage| now - usage.time > size::MAX_AGE) {
+            stale.push(path);
+        }
+        if usage.is_some() && item.disk_usage().is_none() {
+            item.set_disk_usage(usage);
+            model.items_changed(position, 1, 1);
+        }
+    }
+    stale.sort();
+    stale.dedup();
+    let ignore = app_config.size_ignore().to_vec();
+    let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+    std::thread::spawn(move || {
+        for path in stale {
+            let usage = size::scan(Path::new(&path), &ignore, now);
+            if sender.send(Some((path, usage))).is_err() {
+                return;
+            }
+        }
+        let _ = sender.send(None);
+    });
+    receiver.attach(
+        None,
+        glib::clone!(@weak model => @default-return glib::Continue(false), move |scanned| {
+            let _running = &running;
+            let (path, usage) = match scanned {
+                Some(scanned) => scanned,
+                None => {
+                    if let Err(error) = size::save_cache(&cache) {
+                        eprintln!("{}", error);
+                    }
+                    return glib::Continue(false);
+                }
+            };
+            for position in 0..model.n_items() {
+                let item = model.item(position).and_downcast::<ProjectInfo>().unwrap();
+                if item.path() == path {
+                    item.set_disk_usage(Some(usage));
+                    model.items_changed(position, 1, 1);
+                }
+            }
+            cache.insert(path, usage);
+            glib::Continue(true)
+        }),
+    );
+}
+
+/// Launches `item` and records a session, which ends when the launched process exits. Launchers
+/// that hand off to a running instance exit right away, and applications started over D-Bus have
+/// no process to watch, so those sessions only record the open.
+fn open_project(provider: &ProjectProvider, item: &ProjectInfo) -> Result<(), String> {
+    let pid = command::open_project(
+        &provider.app_config.borrow(),
+        &item.project_type(),
+        item.application().as_deref(),
+        &item.path(),
+    )?;
+    let start = item.start_session();
+    match pid {
+        Some(pid) => {
+            glib::child_watch_add_local(
+                pid,
+                glib::clone!(@strong provider.model as model, @strong provider.unloaded as unloaded, @strong item => move |_, _| {
+                    if item.end_session(start) {
+                        refresh_item(&model, &item);
+                        if let Err(error) = save_projects(&model, &unloaded.borrow(), &item) {
+                            eprintln!("{}", error);
+                        }
+                    }
+                }),
+            );
+        }
+        None => {
+            item.end_session(start);
+        }
+    }
+    refresh_item(&provider.model, item);
+    save_projects(&provider.model, &provider.unloaded.borrow(), &item)
+}
+
+/// Ends sessions left running by an earlier run, as their processes can't be watched any more.
+fn end_stale_sessions(provider: &ProjectProvider) -> Vec<String> {
+    // One changed item per collection, as saving it writes its whole collection
+    let mut changed = BTreeMap::new();
+    for position in 0..provider.model.n_items() {
+        let item = provider
+            .model
+            .item(position)
+            .and_downcast::<ProjectInfo>()
+            .unwrap();
+        if item.end_running_sessions(Some(SESSION_MAX_DURATION)) {
+            changed.insert(item.collection(), item);
+        }
+    }
+    changed
+        .values()
+        .filter_map(|item| save_projects(&provider.model, &provider.unloaded.borrow(), item).err())
+        .collect()
+}
+
+fn same_path(a: &Path, b: &Path) -> bool {
+    match (std::fs::canonicalize(a), std::fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Adds a project for `path` to the collection being shown, returning its position.
+fn add_project(
+    provider: &ProjectProvider,
+    path: &Path,
+    project_type: Option<&str>,
+) -> Result<u32, String> {
+    let app_config = provider.app_config.borrow();
+    let shown_collection = provider.filter_state.borrow().collection.clone();
+    let collection = app_config
+        .collections()
+        .iter()
+        .find(|c| Some(&c.name) == shown_collection.as_ref())
+        .unwrap_or(&app_config.collections()[0]);
+    if provider.unloaded.borrow().contains(&collection.name) {
+        return Err(format!("Collection \"{}\" was not loaded", collection.name));
+    }
+    let now = glib::DateTime::now_utc().unwrap().to_unix();
+    let mut info = config::ProjectInfo::new(&app_config, path, now)?;
+    if let Some(project_type) = project_type {
+        info.project_type = project_type.to_owned();
+    }
+    let exists = (0..provider.model.n_items())
+        .filter_map(|position| provider.model.item(position).and_downcast::<ProjectInfo>())
+        .any(|item| item.collection() == collection.name && item.name() == info.name);
+    if exists {
+        return Err(format!(
+            "A project named \"{}\" already exists in {}",
+            info.name, collection.name
+        ));
+    }
+    let item = ProjectInfo::new(ProjectInfoInner::from_config(info, collection, &app_config));
+    provider.model.append(&item);
+    health::validate(&provider.model, &app_config);
+    if let Err(error) = save_projects(&provider.model, &provider.unloaded.borrow(), &item) {
+        provider.model.remove(provider.model.n_items() - 1);
+        return Err(error);
+    }
+    Ok(provider.model.n_items() - 1)
+}
+
+/// Selects the project at `path`, or offers to add one.
+fn open_path(app_window: &gtk::ApplicationWindow, provider: &ProjectProvider, path: &Path) {
+    let position = (0..provider.model.n_items()).find(|&position| {
+        let item = provider.model.item(position).and_downcast::<ProjectInfo>();
+        item.map_or(false, |item| same_path(Path::new(&item.path()), path))
+    });
+    if let Some(position) = position {
+        gio::prelude::ActionGroupExt::activate_action(
+            app_window,
+            "select-project",
+            Some(&position.to_variant()),
+        );
+        return;
+    }
+    let dialog = gtk::AlertDialog::builder()
+        .modal(true)
+        .message("Add Project?")
+        .detail(format!("{} is not in the project list.", path.display()))
+        .cancel_button(0)
+        .default_button(1)
+        .build();
+    dialog.set_buttons(&["Cancel", "Add Project"]);
+    let path = path.to_owned();
+    dialog.choose(
+        Some(app_window),
+        gio::Cancellable::NONE,
+        glib::clone!(@weak app_window, @strong provider => move |result| {
+            if let Ok(1) = result {
+                match add_project(&provider, &path, None) {
+                    Ok(position) => gio::prelude::ActionGroupExt::activate_action(
+                        &app_window,
+                        "select-project",
+                        Some(&position.to_variant()),
+                    ),
+                    Err(error) => show_error(&app_window, "Error adding project", &error),
+                }
+            }
+        }),
+    );
+}
+
+fn build_window(app: &gtk::Application, provider: &ProjectProvider) -> gtk::ApplicationWindow {
     let (pane, view) = build_column_view(provider, 240);
 
+    let tag_list = gtk::StringList::new(&[]);
+    let tag_dropdown = gtk::DropDown::builder()
+        .model(&tag_list)
+        .tooltip_text("Filter by tag")
+        .build();
+    refresh_tag_list(&tag_list, &tag_dropdown, &provider.model);
+    provider.model.connect_items_changed(
+        glib::clone!(@weak tag_list, @weak tag_dropdown => move |model, _, _, _| {
+            refresh_tag_list(&tag_list, &tag_dropdown, model);
+        }),
+    );
+    tag_dropdown.connect_selected_notify(glib::clone!(@strong provider => move |tag_dropdown| {
+        let tag = match tag_dropdown.selected() {
+            0 => None,
+            _ => tag_dropdown
+                .selected_item()
+                .and_downcast::<gtk::StringObject>()
+                .map(|tag| tag.string().into()),
+        };
+        provider.set_tag_filter(tag);
+    }));
+
+    let collection_list = gtk::StringList::new(&[]);
+    let collection_dropdown = gtk::DropDown::builder()
+        .model(&collection_list)
+        .tooltip_text("Collection")
+        .build();
+    refresh_collection_list(
+        &collection_list,
+        &collection_dropdown,
+        &provider.app_config.borrow(),
+    );
+    select_collection(provider, &view, &collection_dropdown);
+    provider.model.connect_items_changed(
+        glib::clone!(@weak collection_list, @weak collection_dropdown, @strong provider => move |_, _, _, _| {
+            refresh_collection_list(&collection_list, &collection_dropdown, &provider.app_config.borrow());
+        }),
+    );
+    collection_dropdown.connect_selected_notify(
+        glib::clone!(@strong provider, @weak view => move |collection_dropdown| {
+            select_collection(&provider, &view, collection_dropdown);
+        }),
+    );
+
+    let stack = gtk::Stack::new();
+    stack.add_titled(&pane, Some("list"), "List");
+    stack.add_titled(&board::build_board(provider), Some("board"), "Board");
+    let switcher = gtk::StackSwitcher::builder().stack(&stack).build();
+
+    let menu = gio::Menu::new();
+    menu.append(Some("New from Template…"), Some("win.new-from-template"));
+    menu.append(Some("Show Archived"), Some("win.show-archived"));
+    menu.append(Some("Statistics"), Some("win.statistics"));
+    menu.append(Some("Export Timesheet…"), Some("win.export-timesheet"));
+    menu.append(Some("Restore from Backup…"), Some("win.restore-backup"));
+    let menu_button = gtk::MenuButton::builder()
+        .icon_name("open-menu-symbolic")
+        .menu_model(&menu)
+        .build();
+
+    let header = gtk::HeaderBar::new();
+    header.pack_start(&collection_dropdown);
+    header.pack_start(&tag_dropdown);
+    header.pack_end(&menu_button);
+    header.pack_end(&switcher);
+
     let app_window = gtk::ApplicationWindow::builder()
         .application(app)
         .title("Projects")
         .icon_name("applications-development")
         .default_width(640)
         .default_height(480)
-        .child(&pane)
+        .titlebar(&header)
+        .child(&stack)
         .build();
+    // Notes are saved while the window is still there to show errors in
+    app_window.connect_close_request(glib::clone!(@strong provider => move |_| {
+        flush_notes(&provider.model, &provider.unloaded.borrow(), &provider.pending_notes);
+        gtk::Inhibit(false)
+    }));
     app_window.present();
 
-    let app_config = provider.app_config.clone();
-    view.connect_activate(move |view, position| {
+    let run_command = gio::SimpleAction::new("run-command", Some(glib::VariantTy::STRING));
+    run_command.connect_activate(
+        glib::clone!(@weak app_window, @weak view => move |_, parameter| {
+            let name = parameter.and_then(|parameter| parameter.get::<String>());
+            let item = view
+                .model()
+                .and_downcast::<gtk::SingleSelection>()
+                .and_then(|model| model.selected_item())
+                .and_downcast::<ProjectInfo>();
+            if let (Some(name), Some(item)) = (name, item) {
+                if let Some(command) = item.commands().iter().find(|c| c.name == name) {
+                    if let Err(error) = command::run_command(command, &item) {
+                        show_error(&app_window, "Error running command", &error);
+                    }
+                }
+            }
+        }),
+    );
+    app_window.add_action(&run_command);
+
+    let show_archived = gio::SimpleAction::new_stateful("show-archived", None, &false.to_variant());
+    show_archived.connect_change_state(glib::clone!(@strong provider => move |action, state| {
+        if let Some(state) = state {
+            action.set_state(state);
+            provider.set_show_archived(state.get::<bool>().unwrap_or(false));
+        }
+    }));
+    app_window.add_action(&show_archived);
+
+    let statistics = gio::SimpleAction::new("statistics", None);
+    statistics.connect_activate(
+        glib::clone!(@weak app_window, @strong provider => move |_, _| {
+            stats::show_statistics(app_window.upcast_ref(), &provider);
+        }),
+    );
+    app_window.add_action(&statistics);
+
+    let new_from_template = gio::SimpleAction::new("new-from-template", None);
+    new_from_template.connect_activate(
+        glib::clone!(@weak app_window, @strong provider => move |_, _| {
+            template::show_dialog(&app_window, &provider);
+        }),
+    );
+    app_window.add_action(&new_from_template);
+
+    let export_timesheet = gio::SimpleAction::new("export-timesheet", None);
+    export_timesheet.connect_activate(
+        glib::clone!(@weak app_window, @strong provider => move |_, _| {
+            timesheet::export(&app_window, &provider);
+        }),
+    );
+    app_window.add_action(&export_timesheet);
+
+    let restore_backup = gio::SimpleAction::new("restore-backup", None);
+    restore_backup.connect_activate(
+        glib::clone!(@weak app_window, @strong provider => move |_, _| {
+            backup::show_restore_dialog(&app_window, &provider);
+        }),
+    );
+    app_window.add_action(&restore_backup);
+
+    // Selects a project by its position in the unfiltered model
+    let select_project = gio::SimpleAction::new("select-project", Some(glib::VariantTy::UINT32));
+    select_project.connect_activate(
+        glib::clone!(@strong provider, @weak view, @weak stack => move |_, parameter| {
+            let item = parameter
+                .and_then(|parameter| parameter.get::<u32>())
+                .and_then(|position| provider.model.item(position));
+            let selection = view.model().and_downcast::<gtk::SingleSelection>();
+            if let (Some(item), Some(selection)) = (item, selection) {
+                let position = (0..selection.n_items())
+                    .find(|&position| selection.item(position).as_ref() == Some(&item));
+                if let Some(position) = position {
+                    selection.set_selected(position);
+                    stack.set_visible_child_name("list");
+                }
+            }
+        }),
+    );
+    app_window.add_action(&select_project);
+
+    view.connect_activate(glib::clone!(@strong provider => move |view, position| {
         let item = view
             .model()
             .unwrap()
             .item(position)
             .and_downcast::<ProjectInfo>();
         if let Some(item) = item {
-            if let Some(application) = app_config
-                .get()
-                .unwrap()
-                .project_type_application(item.project_type())
-            {
-                let file = gtk::gio::File::for_path(item.path());
-                if let Err(error) = application.launch(&[file], gtk::gio::AppLaunchContext::NONE) {
-                    eprintln!("{}", error);
-                }
-            } else {
-                eprintln!(
-                    "Could not find application to open {} project.",
-                    item.project_type()
-                );
+            if let Err(error) = open_project(&provider, &item) {
+                show_error(view, "Error opening project", &error);
             }
         }
-    });
+    }));
+    app_window
 }
 
 fn main() -> glib::ExitCode {
-    let provider = ProjectProvider {
-        app_config: Rc::default(),
-        model: ListStore::new(ProjectInfo::static_type()),
-    };
+    if let Some(exit_code) = cli::run(std::env::args().skip(1).collect()) {
+        return exit_code;
+    }
+    let provider = ProjectProvider::new();
+    let load_errors: Rc<RefCell<Vec<String>>> = Rc::default();
 
-    let app = gtk::Application::builder().application_id(APP_ID).build();
-    app.connect_startup(glib::clone!(@strong provider => move |_| {
-        provider.app_config.set(load_config().unwrap()).map_err(|_| "config loaded multiple times").unwrap();
-        load_projects(&provider.model).unwrap();
+    let app = gtk::Application::builder()
+        .application_id(APP_ID)
+        .flags(gio::ApplicationFlags::HANDLES_OPEN)
+        .build();
+    app.add_main_option(
+        "quick-open",
+        glib::Char::from(b'p'),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::None,
+        "Show the quick open palette, for binding to a desktop shortcut",
+        None,
+    );
+    // Handled through the running instance's action, so a shortcut can bring up the palette
+    app.connect_handle_local_options(|app, options| {
+        if !options.contains("quick-open") {
+            return -1;
+        }
+        if let Err(error) = app.register(gio::Cancellable::NONE) {
+            eprintln!("{}", error);
+            return 1;
+        }
+        app.activate_action("quick-open", None);
+        if app.is_remote() {
+            0
+        } else {
+            -1
+        }
+    });
+    app.connect_startup(glib::clone!(@strong provider, @strong load_errors => move |app| {
+        let (app_config, errors) = load_config();
+        load_errors.borrow_mut().extend(errors);
+        provider.app_config.replace(app_config);
+        gtk::style_context_add_provider_for_display(
+            &gtk::gdk::Display::default().expect("could not connect to a display"),
+            &provider.status_css,
+            gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
+        );
+        provider.load_status_css();
+        let errors = load_projects(
+            &provider.app_config.borrow(),
+            &provider.model,
+            &provider.unloaded,
+        );
+        load_errors.borrow_mut().extend(errors);
+        let errors = end_stale_sessions(&provider);
+        load_errors.borrow_mut().extend(errors);
+        watch_files(app, &provider);
+        if let Err(error) = dbus::export(app, &provider) {
+            load_errors.borrow_mut().push(error);
+        }
+        let quick_open = gio::SimpleAction::new("quick-open", None);
+        quick_open.connect_activate(glib::clone!(@weak app, @strong provider => move |_, _| {
+            app.activate();
+            if let Some(app_window) = app.active_window() {
+                palette::show(&app_window, &provider);
+            }
+        }));
+        app.add_action(&quick_open);
+        app.set_accels_for_action("app.quick-open", &["<Control>p"]);
+        refresh_notes(&provider.model);
+        refresh_sizes(&provider.model, &provider.app_config.borrow());
+        glib::timeout_add_seconds_local(
+            SIZE_REFRESH_INTERVAL,
+            glib::clone!(@strong provider => @default-return glib::Continue(false), move || {
+                refresh_sizes(&provider.model, &provider.app_config.borrow());
+                glib::Continue(true)
+            }),
+        );
+        refresh_vcs(&provider.model, &provider.app_config.borrow());
+        glib::timeout_add_seconds_local(
+            VCS_REFRESH_INTERVAL,
+            glib::clone!(@weak provider.model as model, @strong provider.app_config as app_config => @default-return glib::Continue(false), move || {
+                refresh_vcs(&model, &app_config.borrow());
+                glib::Continue(true)
+            }),
+        );
     }));
-    app.connect_shutdown(|_| {}); // TODO save projects
-    app.connect_activate(move |app| {
-        build_window(app, &provider);
+    app.connect_shutdown(glib::clone!(@strong provider => move |_| {
+        flush_notes(&provider.model, &provider.unloaded.borrow(), &provider.pending_notes);
+    }));
+    app.connect_activate(glib::clone!(@strong provider => move |app| {
+        if let Some(app_window) = app.active_window() {
+            app_window.present();
+            return;
+        }
+        let app_window = build_window(app, &provider);
+        let errors = load_errors.take();
+        if !errors.is_empty() {
+            show_error(&app_window, "Error loading projects", &errors.join("\n\n"));
+        }
+    }));
+    app.connect_open(move |app, files, _| {
+        app.activate();
+        let app_window = app.active_window().and_downcast::<gtk::ApplicationWindow>();
+        if let Some(app_window) = app_window {
+            for path in files.iter().filter_map(|file| file.path()) {
+                open_path(&app_window, &provider, &path);
+            }
+        }
     });
     app.run()
 }
@@ -0,0 +1,96 @@
+use std::{
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use gtk::{pango, prelude::*};
+
+pub fn write_notes(path: &Path, notes: &str) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|error| format!("Error creating {}: {}", parent.display(), error))?;
+    }
+    std::fs::write(path, notes)
+        .map_err(|error| format!("Error writing {}: {}", path.display(), error))
+}
+
+/// The first non-empty line, without heading markers, for showing notes in a single row.
+pub fn summary(notes: &str) -> String {
+    notes
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(|line| line.trim_start_matches('#').trim().to_owned())
+        .unwrap_or_default()
+}
+
+/// The summary of the notes in `path`, reading only up to its first non-empty line. `None` if
+/// there's no notes file.
+pub fn read_summary(path: &Path) -> Option<String> {
+    let file = std::fs::File::open(path).ok()?;
+    let line = BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .find(|line| !line.trim().is_empty())
+        .unwrap_or_default();
+    Some(summary(&line))
+}
+
+pub fn create_tags(buffer: &gtk::TextBuffer) {
+    buffer.create_tag(Some("heading"), &[("weight", &700), ("scale", &1.2)]);
+    buffer.create_tag(Some("bold"), &[("weight", &700)]);
+    buffer.create_tag(Some("code"), &[("family", &"monospace")]);
+    buffer.create_tag(Some("quote"), &[("style", &pango::Style::Italic)]);
+}
+
+/// Character ranges enclosed by pairs of `delimiter`, including the delimiters.
+fn spans(line: &str, delimiter: &str) -> Vec<(i32, i32)> {
+    let offsets: Vec<i32> = line
+        .match_indices(delimiter)
+        .map(|(index, _)| line[..index].chars().count() as i32)
+        .collect();
+    let length = delimiter.chars().count() as i32;
+    offsets
+        .chunks_exact(2)
+        .map(|pair| (pair[0], pair[1] + length))
+        .collect()
+}
+
+/// Re-applies the Markdown tags created by `create_tags` to the whole buffer.
+pub fn highlight(buffer: &gtk::TextBuffer) {
+    let (start, end) = buffer.bounds();
+    buffer.remove_all_tags(&start, &end);
+    let text = buffer.text(&start, &end, false);
+    let apply = |tag: &str, from: i32, to: i32| {
+        buffer.apply_tag_by_name(
+            tag,
+            &buffer.iter_at_offset(from),
+            &buffer.iter_at_offset(to),
+        );
+    };
+    let mut offset = 0;
+    let mut in_code_block = false;
+    for line in text.split('\n') {
+        let length = line.chars().count() as i32;
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") {
+            apply("code", offset, offset + length);
+            in_code_block = !in_code_block;
+        } else if in_code_block {
+            apply("code", offset, offset + length);
+        } else if trimmed.starts_with('#') {
+            apply("heading", offset, offset + length);
+        } else {
+            if trimmed.starts_with('>') {
+                apply("quote", offset, offset + length);
+            }
+            for (from, to) in spans(line, "**") {
+                apply("bold", offset + from, offset + to);
+            }
+            for (from, to) in spans(line, "`") {
+                apply("code", offset + from, offset + to);
+            }
+        }
+        offset += length + 1;
+    }
+}
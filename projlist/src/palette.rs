@@ -0,0 +1,214 @@
+use std::{cell::RefCell, rc::Rc};
+
+use gtk::{gdk, glib, prelude::*};
+
+use crate::{open_project, project_info::ProjectInfo, show_error, ProjectProvider};
+
+const MAX_RESULTS: usize = 20;
+
+/// Scores `text` by how well `query` matches it as a subsequence, or None if it doesn't.
+/// Consecutive characters and matches at the start of words score higher.
+fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous_match: Option<usize> = None;
+    for query_char in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = text[position..].iter().position(|&c| c == query_char)? + position;
+        score += 1;
+        if previous_match == Some(found.wrapping_sub(1)) {
+            score += 5;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        previous_match = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+fn project_score(query: &str, item: &ProjectInfo) -> Option<i64> {
+    let name = fuzzy_score(query, &item.name()).map(|score| score * 2);
+    let path = fuzzy_score(query, &item.short_path());
+    let tags = item
+        .tags()
+        .iter()
+        .filter_map(|tag| fuzzy_score(query, tag))
+        .max();
+    [name, path, tags].into_iter().flatten().max()
+}
+
+fn recency_bonus(item: &ProjectInfo) -> i64 {
+    let now = glib::DateTime::now_utc().unwrap().to_unix();
+    let days = match item.last_opened() {
+        Some(last_opened) => (now - last_opened.to_unix()) / (24 * 60 * 60),
+        None => return 0,
+    };
+    match days {
+        0..=1 => 4,
+        2..=7 => 2,
+        8..=30 => 1,
+        _ => 0,
+    }
+}
+
+/// Projects matching `query`, best first. Recently opened projects get a bonus, and more recent
+/// projects come first among equal scores.
+fn search(provider: &ProjectProvider, query: &str) -> Vec<ProjectInfo> {
+    let mut results: Vec<(i64, ProjectInfo)> = (0..provider.model.n_items())
+        .filter_map(|position| provider.model.item(position).and_downcast::<ProjectInfo>())
+        .filter(|item| !item.archived())
+        .filter_map(|item| {
+            project_score(query, &item).map(|score| (score + recency_bonus(&item), item))
+        })
+        .collect();
+    results.sort_by(|(a_score, a), (b_score, b)| {
+        b_score
+            .cmp(a_score)
+            .then_with(|| b.last_opened().cmp(&a.last_opened()))
+    });
+    results
+        .into_iter()
+        .take(MAX_RESULTS)
+        .map(|(_, item)| item)
+        .collect()
+}
+
+fn refresh_results(
+    list: &gtk::ListBox,
+    results: &RefCell<Vec<ProjectInfo>>,
+    provider: &ProjectProvider,
+    query: &str,
+) {
+    while let Some(child) = list.first_child() {
+        list.remove(&child);
+    }
+    results.replace(search(provider, query));
+    for item in results.borrow().iter() {
+        let label = gtk::Label::builder().xalign(0.0).build();
+        label.set_markup(&format!(
+            "<b>{}</b>\n<small>{}</small>",
+            glib::markup_escape_text(&item.name()),
+            glib::markup_escape_text(&item.short_path())
+        ));
+        list.append(&label);
+    }
+    list.select_row(list.row_at_index(0).as_ref());
+}
+
+fn move_selection(list: &gtk::ListBox, offset: i32) {
+    let index = list.selected_row().map_or(0, |row| row.index() + offset);
+    if let Some(row) = list.row_at_index(index) {
+        list.select_row(Some(&row));
+    }
+}
+
+/// Shows a popup for finding and launching a project by typing part of its name, path or tags.
+pub fn show(app_window: &gtk::Window, provider: &ProjectProvider) {
+    let entry = gtk::SearchEntry::builder()
+        .placeholder_text("Open project")
+        .build();
+    let list = gtk::ListBox::new();
+    let scrolled = gtk::ScrolledWindow::builder()
+        .hscrollbar_policy(gtk::PolicyType::Never)
+        .min_content_height(320)
+        .child(&list)
+        .build();
+    let content = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(6)
+        .margin_top(6)
+        .margin_bottom(6)
+        .margin_start(6)
+        .margin_end(6)
+        .build();
+    content.append(&entry);
+    content.append(&scrolled);
+    let window = gtk::Window::builder()
+        .title("Quick Open")
+        .transient_for(app_window)
+        .modal(true)
+        .decorated(false)
+        .default_width(480)
+        .child(&content)
+        .build();
+
+    let results: Rc<RefCell<Vec<ProjectInfo>>> = Rc::default();
+    refresh_results(&list, &results, provider, "");
+    entry.connect_search_changed(
+        glib::clone!(@weak list, @strong results, @strong provider => move |entry| {
+            refresh_results(&list, &results, &provider, &entry.text());
+        }),
+    );
+
+    let launch = Rc::new(
+        glib::clone!(@weak window, @weak app_window, @strong results, @strong provider => move |index: i32| {
+            let item = results.borrow().get(index as usize).cloned();
+            if let Some(item) = item {
+                window.close();
+                if let Err(error) = open_project(&provider, &item) {
+                    show_error(&app_window, "Error opening project", &error);
+                }
+            }
+        }),
+    );
+    entry.connect_activate(glib::clone!(@weak list, @strong launch => move |_| {
+        launch(list.selected_row().map_or(0, |row| row.index()));
+    }));
+    list.connect_row_activated(glib::clone!(@strong launch => move |_, row| {
+        launch(row.index());
+    }));
+    entry.connect_stop_search(glib::clone!(@weak window => move |_| window.close()));
+
+    let keys = gtk::EventControllerKey::new();
+    keys.connect_key_pressed(
+        glib::clone!(@weak list => @default-return gtk::Inhibit(false), move |_, key, _, _| {
+            match key {
+                gdk::Key::Down => move_selection(&list, 1),
+                gdk::Key::Up => move_selection(&list, -1),
+                _ => return gtk::Inhibit(false),
+            }
+            gtk::Inhibit(true)
+        }),
+    );
+    entry.add_controller(keys);
+
+    window.present();
+    entry.grab_focus();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_input() {
+        assert_eq!(fuzzy_score("", "projlist"), Some(0));
+        assert_eq!(fuzzy_score("", ""), Some(0));
+        assert_eq!(fuzzy_score("  ", "projlist"), Some(0));
+        assert_eq!(fuzzy_score("p", ""), None);
+    }
+
+    #[test]
+    fn subsequences() {
+        assert!(fuzzy_score("pjl", "projlist").is_some());
+        assert!(fuzzy_score("PROJ", "projlist").is_some());
+        assert!(fuzzy_score("proj list", "projlist").is_some());
+        assert_eq!(fuzzy_score("lp", "pl"), None);
+        assert_eq!(fuzzy_score("projx", "projlist"), None);
+    }
+
+    #[test]
+    fn consecutive_matches_score_higher() {
+        assert_eq!(fuzzy_score("abc", "abc"), Some(16));
+        assert_eq!(fuzzy_score("abc", "axbxc"), Some(6));
+        assert!(fuzzy_score("list", "projlist") > fuzzy_score("list", "lib-install-test"));
+    }
+
+    #[test]
+    fn word_starts_score_higher() {
+        assert!(fuzzy_score("b", "a-b") > fuzzy_score("b", "ab"));
+        assert!(fuzzy_score("dl", "dir-list") > fuzzy_score("dl", "idle"));
+    }
+}
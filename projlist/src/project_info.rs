@@ -1,17 +1,28 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
 use glib::Object;
 use gtk::{glib, subclass::prelude::ObjectSubclassIsExt};
 
-use crate::config;
+use crate::{
+    config::{self, AppConfig, Collection, ProjectCommand, ProjectLink, Session, StatusChange},
+    health::Problem,
+    size::DiskUsage,
+    vcs::VcsStatus,
+};
 
 mod imp {
-    use once_cell::unsync::OnceCell;
+    use std::cell::RefCell;
 
     use gtk::glib;
     use gtk::subclass::prelude::*;
 
     #[derive(Default)]
     pub struct ProjectInfo {
-        pub inner: OnceCell<super::ProjectInfoInner>,
+        pub inner: RefCell<super::ProjectInfoInner>,
+        pub vcs_status: RefCell<Option<crate::vcs::VcsStatus>>,
+        pub problems: RefCell<Vec<crate::health::Problem>>,
+        pub disk_usage: RefCell<Option<crate::size::DiskUsage>>,
+        pub notes_summary: RefCell<String>,
     }
 
     #[glib::object_subclass]
@@ -24,37 +35,77 @@ mod imp {
     impl ObjectImpl for ProjectInfo {}
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct ProjectInfoInner {
+    collection: String,
+    projects_file: PathBuf,
     name: String,
     project_type: String,
     status: String,
+    status_history: Vec<StatusChange>,
     last_opened: Option<glib::DateTime>,
     path: String,
     short_path: String,
+    notes_file: PathBuf,
     notes: String,
+    notes_inline: bool,
+    /// Whether `notes_file` was read yet. It's read the first time the notes are shown, instead of
+    /// for every project while loading.
+    notes_loaded: bool,
+    tags: Vec<String>,
+    fields: BTreeMap<String, String>,
+    custom_commands: Vec<ProjectCommand>,
+    commands: Vec<ProjectCommand>,
+    application: Option<String>,
+    archived: bool,
+    sessions: Vec<Session>,
+    links: Vec<ProjectLink>,
+}
+
+fn short_path(path: &str) -> String {
+    let home_dir = gtk::glib::home_dir();
+    match path.strip_prefix(&*home_dir.to_string_lossy()) {
+        Some(s) => format!("~{}", s),
+        None => path.to_owned(),
+    }
 }
 
 impl ProjectInfoInner {
-    pub fn from_config(info: config::ProjectInfo) -> Self {
+    pub fn from_config(
+        info: config::ProjectInfo,
+        collection: &Collection,
+        app_config: &AppConfig,
+    ) -> Self {
         let last_opened = if info.last_opened == 0 {
             None
         } else {
             Some(glib::DateTime::from_unix_utc(info.last_opened).unwrap())
         };
-        let mut short_path = info.path.clone();
-        let home_dir = gtk::glib::home_dir();
-        if let Some(s) = short_path.strip_prefix(&*home_dir.to_string_lossy()) {
-            short_path = format!("~{}", s);
-        }
+        let short_path = short_path(&info.path);
+        let commands = app_config.project_commands(&info.project_type, &info.command);
+        let notes_file = collection.notes_file(&info.name, &info.path);
         ProjectInfoInner {
+            collection: collection.name.clone(),
+            projects_file: collection.projects_file(),
             name: info.name,
             project_type: info.project_type,
             status: info.status,
+            status_history: info.history,
             last_opened,
             path: info.path,
             short_path,
+            notes_file,
             notes: info.notes,
+            notes_inline: true,
+            notes_loaded: false,
+            tags: info.tags,
+            fields: info.fields,
+            custom_commands: info.command,
+            commands,
+            application: info.application,
+            archived: info.archived,
+            sessions: info.session,
+            links: info.link,
         }
     }
 }
@@ -66,30 +117,244 @@ glib::wrapper! {
 impl ProjectInfo {
     pub fn new(inner: ProjectInfoInner) -> Self {
         let info: Self = Object::builder().build();
-        info.imp().inner.set(inner).unwrap();
+        info.imp().inner.replace(inner);
         info
     }
-    pub fn name(&self) -> &str {
-        &self.imp().inner.get().unwrap().name
+    pub fn update(&self, inner: ProjectInfoInner) {
+        self.imp().inner.replace(inner);
+    }
+    pub fn to_config(&self) -> config::ProjectInfo {
+        let inner = self.imp().inner.borrow();
+        config::ProjectInfo {
+            name: inner.name.clone(),
+            project_type: inner.project_type.clone(),
+            status: inner.status.clone(),
+            last_opened: inner
+                .last_opened
+                .as_ref()
+                .map(|dt| dt.to_unix())
+                .unwrap_or(0),
+            path: inner.path.clone(),
+            notes: if inner.notes_inline {
+                inner.notes.clone()
+            } else {
+                String::new()
+            },
+            tags: inner.tags.clone(),
+            fields: inner.fields.clone(),
+            command: inner.custom_commands.clone(),
+            history: inner.status_history.clone(),
+            application: inner.application.clone(),
+            archived: inner.archived,
+            session: inner.sessions.clone(),
+            link: inner.links.clone(),
+        }
     }
-    pub fn project_type(&self) -> &str {
-        &self.imp().inner.get().unwrap().project_type
+    pub fn collection(&self) -> String {
+        self.imp().inner.borrow().collection.clone()
     }
-    pub fn status(&self) -> &str {
-        &self.imp().inner.get().unwrap().status
+    pub fn projects_file(&self) -> PathBuf {
+        self.imp().inner.borrow().projects_file.clone()
+    }
+    pub fn name(&self) -> String {
+        self.imp().inner.borrow().name.clone()
+    }
+    pub fn project_type(&self) -> String {
+        self.imp().inner.borrow().project_type.clone()
+    }
+    pub fn status(&self) -> String {
+        self.imp().inner.borrow().status.clone()
+    }
+    pub fn status_history(&self) -> Vec<StatusChange> {
+        self.imp().inner.borrow().status_history.clone()
+    }
+    pub fn set_status(&self, status: &str) {
+        let mut inner = self.imp().inner.borrow_mut();
+        inner.status = status.to_owned();
+        inner.status_history.push(StatusChange {
+            status: status.to_owned(),
+            time: glib::DateTime::now_utc().unwrap().to_unix(),
+        });
     }
     pub fn last_opened(&self) -> Option<glib::DateTime> {
-        self.imp().inner.get().unwrap().last_opened.clone()
+        self.imp().inner.borrow().last_opened.clone()
+    }
+    pub fn sessions(&self) -> Vec<Session> {
+        self.imp().inner.borrow().sessions.clone()
     }
-    pub fn path(&self) -> &str {
-        &self.imp().inner.get().unwrap().path
+    /// Starts a session and marks the project as opened, returning the session's start time.
+    pub fn start_session(&self) -> i64 {
+        let now = glib::DateTime::now_utc().unwrap();
+        let mut inner = self.imp().inner.borrow_mut();
+        inner.sessions.push(Session {
+            start: now.to_unix(),
+            end: None,
+        });
+        inner.last_opened = Some(now.clone());
+        now.to_unix()
     }
-    pub fn short_path(&self) -> &str {
-        &self.imp().inner.get().unwrap().short_path
+    /// Ends the running session that started at `start`, returning false if there isn't one.
+    pub fn end_session(&self, start: i64) -> bool {
+        let now = glib::DateTime::now_utc().unwrap().to_unix();
+        let mut inner = self.imp().inner.borrow_mut();
+        let session = inner
+            .sessions
+            .iter_mut()
+            .find(|s| s.start == start && s.end.is_none());
+        match session {
+            Some(session) => {
+                session.end = Some(now);
+                true
+            }
+            None => false,
+        }
+    }
+    /// Ends every running session now, or `max_duration` seconds after it started if that's
+    /// earlier. Returns false if none were running.
+    pub fn end_running_sessions(&self, max_duration: Option<i64>) -> bool {
+        let now = glib::DateTime::now_utc().unwrap().to_unix();
+        let mut inner = self.imp().inner.borrow_mut();
+        let mut ended = false;
+        for session in inner.sessions.iter_mut().filter(|s| s.end.is_none()) {
+            let end =
+                max_duration.map_or(now, |max_duration| now.min(session.start + max_duration));
+            session.end = Some(end);
+            ended = true;
+        }
+        ended
     }
-    pub fn notes(&self) -> &str {
-        &self.imp().inner.get().unwrap().notes
+    pub fn path(&self) -> String {
+        self.imp().inner.borrow().path.clone()
+    }
+    pub fn short_path(&self) -> String {
+        self.imp().inner.borrow().short_path.clone()
+    }
+    /// Moves the project, along with its notes file if that lives in the project folder.
+    pub fn set_path(&self, path: &str) {
+        let mut inner = self.imp().inner.borrow_mut();
+        let notes_file = inner
+            .notes_file
+            .strip_prefix(&inner.path)
+            .ok()
+            .map(|relative| PathBuf::from(path).join(relative));
+        if let Some(notes_file) = notes_file {
+            inner.notes_file = notes_file;
+        }
+        inner.path = path.to_owned();
+        inner.short_path = short_path(path);
+    }
+    pub fn notes_file(&self) -> PathBuf {
+        self.imp().inner.borrow().notes_file.clone()
+    }
+    pub fn notes(&self) -> String {
+        let mut inner = self.imp().inner.borrow_mut();
+        if !inner.notes_loaded {
+            // Notes kept in the projects file are used until a notes file is written
+            if let Ok(notes) = std::fs::read_to_string(&inner.notes_file) {
+                inner.notes = notes;
+                inner.notes_inline = false;
+            }
+            inner.notes_loaded = true;
+        }
+        inner.notes.clone()
+    }
+    pub fn set_notes(&self, notes: &str) -> Result<(), String> {
+        let notes_file = self.notes_file();
+        crate::notes::write_notes(&notes_file, notes)?;
+        let mut inner = self.imp().inner.borrow_mut();
+        inner.notes = notes.to_owned();
+        inner.notes_inline = false;
+        inner.notes_loaded = true;
+        self.imp()
+            .notes_summary
+            .replace(crate::notes::summary(notes));
+        Ok(())
+    }
+    /// Notes kept in the projects file, which are shown until a notes file is written.
+    pub fn inline_notes(&self) -> String {
+        let inner = self.imp().inner.borrow();
+        if inner.notes_inline {
+            inner.notes.clone()
+        } else {
+            String::new()
+        }
+    }
+    /// The first line of the notes, read in the background so the list doesn't open every notes
+    /// file. Empty until it's been read.
+    pub fn notes_summary(&self) -> String {
+        self.imp().notes_summary.borrow().clone()
+    }
+    pub fn set_notes_summary(&self, summary: String) {
+        self.imp().notes_summary.replace(summary);
+    }
+    pub fn tags(&self) -> Vec<String> {
+        self.imp().inner.borrow().tags.clone()
+    }
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.imp().inner.borrow().tags.iter().any(|t| t == tag)
+    }
+    pub fn add_tag(&self, tag: &str) -> bool {
+        let mut inner = self.imp().inner.borrow_mut();
+        if tag.is_empty() || inner.tags.iter().any(|t| t == tag) {
+            return false;
+        }
+        inner.tags.push(tag.to_owned());
+        true
+    }
+    pub fn remove_tag(&self, tag: &str) {
+        self.imp().inner.borrow_mut().tags.retain(|t| t != tag);
+    }
+    pub fn fields(&self) -> BTreeMap<String, String> {
+        self.imp().inner.borrow().fields.clone()
+    }
+    pub fn field(&self, name: &str) -> Option<String> {
+        self.imp().inner.borrow().fields.get(name).cloned()
+    }
+    pub fn links(&self) -> Vec<ProjectLink> {
+        self.imp().inner.borrow().links.clone()
+    }
+    pub fn add_link(&self, link: ProjectLink) -> bool {
+        let mut inner = self.imp().inner.borrow_mut();
+        if inner.links.contains(&link) {
+            return false;
+        }
+        inner.links.push(link);
+        true
+    }
+    pub fn remove_link(&self, link: &ProjectLink) {
+        self.imp().inner.borrow_mut().links.retain(|l| l != link);
+    }
+    pub fn commands(&self) -> Vec<ProjectCommand> {
+        self.imp().inner.borrow().commands.clone()
+    }
+    pub fn application(&self) -> Option<String> {
+        self.imp().inner.borrow().application.clone()
+    }
+    pub fn set_application(&self, application: Option<String>) {
+        self.imp().inner.borrow_mut().application = application;
+    }
+    pub fn archived(&self) -> bool {
+        self.imp().inner.borrow().archived
+    }
+    pub fn set_archived(&self, archived: bool) {
+        self.imp().inner.borrow_mut().archived = archived;
+    }
+    pub fn problems(&self) -> Vec<Problem> {
+        self.imp().problems.borrow().clone()
+    }
+    pub fn set_problems(&self, problems: Vec<Problem>) {
+        self.imp().problems.replace(problems);
+    }
+    pub fn disk_usage(&self) -> Option<DiskUsage> {
+        *self.imp().disk_usage.borrow()
+    }
+    pub fn set_disk_usage(&self, disk_usage: Option<DiskUsage>) {
+        self.imp().disk_usage.replace(disk_usage);
+    }
+    pub fn vcs_status(&self) -> Option<VcsStatus> {
+        self.imp().vcs_status.borrow().clone()
+    }
+    pub fn set_vcs_status(&self, vcs_status: Option<VcsStatus>) {
+        self.imp().vcs_status.replace(vcs_status);
     }
-
-    pub fn open_command() {}
 }
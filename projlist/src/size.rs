@@ -0,0 +1,93 @@
+use std::{
+    collections::BTreeMap,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+};
+
+use gtk::glib;
+use serde::{Deserialize, Serialize};
+
+use crate::APP_CONFIG_DIR;
+
+/// Sizes older than this are scanned again.
+pub const MAX_AGE: i64 = 24 * 60 * 60;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct DiskUsage {
+    /// Bytes used by the whole project folder.
+    pub size: u64,
+    /// Bytes used by folders that can be regenerated, such as build output.
+    pub reclaimable: u64,
+    /// When the folder was scanned.
+    pub time: i64,
+}
+
+fn cache_file() -> PathBuf {
+    glib::user_cache_dir()
+        .join(APP_CONFIG_DIR)
+        .join("sizes.json")
+}
+
+/// Sizes from earlier scans by project path. A missing or unreadable cache is treated as empty.
+pub fn load_cache() -> BTreeMap<String, DiskUsage> {
+    std::fs::read_to_string(cache_file())
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_cache(cache: &BTreeMap<String, DiskUsage>) -> Result<(), String> {
+    let path = cache_file();
+    let map_err = |error: String| format!("Error writing {}: {}", path.display(), error);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|error| map_err(error.to_string()))?;
+    }
+    let json = serde_json::to_string(cache).map_err(|error| map_err(error.to_string()))?;
+    std::fs::write(&path, json).map_err(|error| map_err(error.to_string()))
+}
+
+/// Bytes used by everything under `path`, and how many of those are in ignored folders.
+/// Symlinks aren't followed and unreadable entries are skipped.
+fn walk(path: &Path, ignore: &[String], ignored: bool) -> (u64, u64) {
+    let mut size = 0;
+    let mut reclaimable = 0;
+    let entries = match std::fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return (0, 0),
+    };
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let metadata = match entry.path().symlink_metadata() {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        // Allocated blocks rather than length, so sparse files count for what they use
+        let used = metadata.blocks() * 512;
+        size += used;
+        if ignored {
+            reclaimable += used;
+        }
+        if metadata.is_dir() {
+            let ignored = ignored
+                || ignore
+                    .iter()
+                    .any(|name| entry.file_name().to_str() == Some(name.as_str()));
+            let (child_size, child_reclaimable) = walk(&entry.path(), ignore, ignored);
+            size += child_size;
+            reclaimable += child_reclaimable;
+        }
+    }
+    (size, reclaimable)
+}
+
+pub fn scan(path: &Path, ignore: &[String], time: i64) -> DiskUsage {
+    let (size, reclaimable) = walk(path, ignore, false);
+    DiskUsage {
+        size,
+        reclaimable,
+        time,
+    }
+}
+
+pub fn format_size(bytes: u64) -> String {
+    glib::format_size(bytes).into()
+}
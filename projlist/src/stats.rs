@@ -0,0 +1,202 @@
+use std::collections::BTreeMap;
+
+use gtk::{glib, prelude::*};
+
+use crate::{health, project_changed, project_info::ProjectInfo, ProjectProvider};
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+const HISTOGRAM_WEEKS: i64 = 12;
+/// Projects not opened for this many days are suggested for archiving.
+pub const STALE_DAYS: i64 = 180;
+
+fn now() -> i64 {
+    glib::DateTime::now_utc().unwrap().to_unix()
+}
+
+/// Projects that were never opened are new rather than stale.
+pub fn is_stale(item: &ProjectInfo, days: i64) -> bool {
+    match item.last_opened() {
+        Some(last_opened) => now() - last_opened.to_unix() > days * SECONDS_PER_DAY,
+        None => false,
+    }
+}
+
+fn projects(provider: &ProjectProvider) -> Vec<ProjectInfo> {
+    (0..provider.model.n_items())
+        .filter_map(|position| provider.model.item(position).and_downcast::<ProjectInfo>())
+        .collect()
+}
+
+fn count_by(
+    items: &[ProjectInfo],
+    key: impl Fn(&ProjectInfo) -> String,
+) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    for item in items {
+        *counts.entry(key(item)).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// How many times projects were opened in each of the past weeks, starting with this week. Each
+/// session is one opening.
+fn opened_per_week(items: &[ProjectInfo]) -> Vec<usize> {
+    let now = now();
+    let mut weeks = vec![0; HISTOGRAM_WEEKS as usize];
+    for session in items.iter().flat_map(ProjectInfo::sessions) {
+        let week = (now - session.start).div_euclid(7 * SECONDS_PER_DAY);
+        if (0..HISTOGRAM_WEEKS).contains(&week) {
+            weeks[week as usize] += 1;
+        }
+    }
+    weeks
+}
+
+fn heading(text: &str) -> gtk::Label {
+    let label = gtk::Label::builder().xalign(0.0).margin_top(6).build();
+    label.set_markup(&format!("<b>{}</b>", glib::markup_escape_text(text)));
+    label
+}
+
+fn counts_grid(counts: BTreeMap<String, usize>) -> gtk::Grid {
+    let grid = gtk::Grid::builder().column_spacing(12).build();
+    for (row, (name, count)) in counts.into_iter().enumerate() {
+        let name = gtk::Label::builder().label(name).xalign(0.0).build();
+        let count = gtk::Label::builder()
+            .label(count.to_string())
+            .xalign(1.0)
+            .build();
+        grid.attach(&name, 0, row as i32, 1, 1);
+        grid.attach(&count, 1, row as i32, 1, 1);
+    }
+    grid
+}
+
+fn histogram(items: &[ProjectInfo]) -> gtk::Grid {
+    let weeks = opened_per_week(items);
+    let max = weeks.iter().copied().max().unwrap_or(0).max(1);
+    let grid = gtk::Grid::builder()
+        .column_spacing(12)
+        .row_spacing(2)
+        .build();
+    for (week, count) in weeks.into_iter().enumerate() {
+        let label = match week {
+            0 => String::from("This week"),
+            1 => String::from("Last week"),
+            _ => format!("{} weeks ago", week),
+        };
+        let label = gtk::Label::builder().label(label).xalign(0.0).build();
+        let bar = gtk::LevelBar::builder()
+            .max_value(max as f64)
+            .value(count as f64)
+            .hexpand(true)
+            .valign(gtk::Align::Center)
+            .build();
+        let count = gtk::Label::builder()
+            .label(count.to_string())
+            .xalign(1.0)
+            .build();
+        grid.attach(&label, 0, week as i32, 1, 1);
+        grid.attach(&bar, 1, week as i32, 1, 1);
+        grid.attach(&count, 2, week as i32, 1, 1);
+    }
+    grid
+}
+
+fn refresh_stale_list(stale_list: &gtk::Box, provider: &ProjectProvider, days: i64) {
+    while let Some(child) = stale_list.first_child() {
+        stale_list.remove(&child);
+    }
+    let stale: Vec<ProjectInfo> = projects(provider)
+        .into_iter()
+        .filter(|item| !item.archived() && is_stale(item, days))
+        .collect();
+    if stale.is_empty() {
+        let label = gtk::Label::builder()
+            .label("None")
+            .xalign(0.0)
+            .css_classes(["dim-label"])
+            .build();
+        stale_list.append(&label);
+    }
+    for item in stale {
+        let row = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+        let name = gtk::Label::builder()
+            .label(item.name())
+            .xalign(0.0)
+            .hexpand(true)
+            .build();
+        row.append(&name);
+        let archive = gtk::Button::with_label("Archive");
+        archive.connect_clicked(
+            glib::clone!(@strong provider, @strong item, @weak stale_list => move |button| {
+                item.set_archived(true);
+                health::validate(&provider.model, &provider.app_config.borrow());
+                project_changed(&provider.model, &provider.unloaded.borrow(), &item, button);
+                refresh_stale_list(&stale_list, &provider, days);
+            }),
+        );
+        row.append(&archive);
+        stale_list.append(&row);
+    }
+}
+
+pub fn show_statistics(parent: &gtk::Window, provider: &ProjectProvider) {
+    let items = projects(provider);
+    let (archived, active): (Vec<ProjectInfo>, Vec<ProjectInfo>) =
+        items.iter().cloned().partition(ProjectInfo::archived);
+
+    let content = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(6)
+        .margin_top(12)
+        .margin_bottom(12)
+        .margin_start(12)
+        .margin_end(12)
+        .build();
+    let summary = gtk::Label::builder()
+        .label(format!(
+            "{} projects, {} archived",
+            active.len(),
+            archived.len()
+        ))
+        .xalign(0.0)
+        .build();
+    content.append(&summary);
+    content.append(&heading("By Status"));
+    content.append(&counts_grid(count_by(&active, ProjectInfo::status)));
+    content.append(&heading("By Type"));
+    content.append(&counts_grid(count_by(&active, ProjectInfo::project_type)));
+    content.append(&heading("Opened per Week"));
+    content.append(&histogram(&items));
+
+    let stale_header = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+    stale_header.append(&heading("Not Opened in Days:"));
+    let days = gtk::SpinButton::with_range(1.0, 3650.0, 1.0);
+    days.set_value(STALE_DAYS as f64);
+    days.set_valign(gtk::Align::End);
+    stale_header.append(&days);
+    content.append(&stale_header);
+    let stale_list = gtk::Box::new(gtk::Orientation::Vertical, 6);
+    refresh_stale_list(&stale_list, provider, STALE_DAYS);
+    days.connect_value_changed(
+        glib::clone!(@strong provider, @weak stale_list => move |days| {
+            refresh_stale_list(&stale_list, &provider, days.value_as_int() as i64);
+        }),
+    );
+    content.append(&stale_list);
+
+    let scrolled = gtk::ScrolledWindow::builder()
+        .hscrollbar_policy(gtk::PolicyType::Never)
+        .child(&content)
+        .build();
+    let window = gtk::Window::builder()
+        .title("Statistics")
+        .transient_for(parent)
+        .modal(true)
+        .default_width(400)
+        .default_height(560)
+        .child(&scrolled)
+        .build();
+    window.present();
+}
@@ -0,0 +1,268 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use rusqlite::{params, Connection};
+
+use crate::config::{self, ProjectInfo, ProjectsConfig};
+
+/// Somewhere a collection's projects are kept.
+pub trait ProjectStore {
+    fn load(&self) -> Result<Vec<ProjectInfo>, String>;
+    fn save(&self, projects: Vec<ProjectInfo>) -> Result<(), String>;
+}
+
+/// A single TOML file with a `[[project]]` table per project.
+pub struct TomlStore {
+    path: PathBuf,
+}
+
+impl ProjectStore for TomlStore {
+    fn load(&self) -> Result<Vec<ProjectInfo>, String> {
+        let projects: ProjectsConfig = config::deserialize(&self.path)?;
+        Ok(projects.project)
+    }
+    fn save(&self, projects: Vec<ProjectInfo>) -> Result<(), String> {
+        config::serialize(&self.path, &ProjectsConfig { project: projects })
+    }
+}
+
+/// A single JSON file with the same layout as `TomlStore`.
+pub struct JsonStore {
+    path: PathBuf,
+}
+
+impl ProjectStore for JsonStore {
+    fn load(&self) -> Result<Vec<ProjectInfo>, String> {
+        let map_err =
+            |error: std::io::Error| format!("Error reading {}: {}", self.path.display(), error);
+        let contents = std::fs::read_to_string(&self.path).map_err(map_err)?;
+        let projects: ProjectsConfig = serde_json::from_str(&contents)
+            .map_err(|error| format!("Error reading {}: {}", self.path.display(), error))?;
+        Ok(projects.project)
+    }
+    fn save(&self, projects: Vec<ProjectInfo>) -> Result<(), String> {
+        let map_err = |error: String| format!("Error writing {}: {}", self.path.display(), error);
+        let json = serde_json::to_string_pretty(&ProjectsConfig { project: projects })
+            .map_err(|error| map_err(error.to_string()))?;
+        std::fs::write(&self.path, json).map_err(|error| map_err(error.to_string()))
+    }
+}
+
+/// An SQLite database with a row per project, keyed by collection and project name. Saving only
+/// writes the rows that changed, so large collections stay fast to update.
+pub struct SqliteStore {
+    path: PathBuf,
+    /// A database can hold several collections, each pointing at it from `collection.toml`.
+    collection: String,
+}
+
+impl SqliteStore {
+    fn connect(&self) -> Result<Connection, rusqlite::Error> {
+        let connection = Connection::open(&self.path)?;
+        // Rows are listed in the order they were added, so removing one doesn't renumber the rest
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS project (
+                collection TEXT NOT NULL,
+                name TEXT NOT NULL,
+                data TEXT NOT NULL,
+                PRIMARY KEY (collection, name)
+            )",
+        )?;
+        Ok(connection)
+    }
+}
+
+impl ProjectStore for SqliteStore {
+    fn load(&self) -> Result<Vec<ProjectInfo>, String> {
+        let map_err = |error: String| format!("Error reading {}: {}", self.path.display(), error);
+        let connection = self.connect().map_err(|error| map_err(error.to_string()))?;
+        let mut statement = connection
+            .prepare("SELECT data FROM project WHERE collection = ?1 ORDER BY rowid")
+            .map_err(|error| map_err(error.to_string()))?;
+        let rows = statement
+            .query_map(params![self.collection], |row| row.get::<_, String>(0))
+            .and_then(|rows| rows.collect::<Result<Vec<String>, _>>())
+            .map_err(|error| map_err(error.to_string()))?;
+        rows.iter()
+            .map(|data| serde_json::from_str(data).map_err(|error| map_err(error.to_string())))
+            .collect()
+    }
+    fn save(&self, projects: Vec<ProjectInfo>) -> Result<(), String> {
+        let map_err = |error: String| format!("Error writing {}: {}", self.path.display(), error);
+        let mut names = HashSet::new();
+        if let Some(project) = projects.iter().find(|project| !names.insert(&project.name)) {
+            return Err(map_err(format!(
+                "more than one project is named \"{}\"",
+                project.name
+            )));
+        }
+        let mut connection = self.connect().map_err(|error| map_err(error.to_string()))?;
+        let transaction = connection
+            .transaction()
+            .map_err(|error| map_err(error.to_string()))?;
+        let existing: Vec<String> = transaction
+            .prepare("SELECT name FROM project WHERE collection = ?1")
+            .and_then(|mut statement| {
+                statement
+                    .query_map(params![self.collection], |row| row.get(0))?
+                    .collect()
+            })
+            .map_err(|error| map_err(error.to_string()))?;
+        for name in existing.iter().filter(|name| !names.contains(name)) {
+            transaction
+                .execute(
+                    "DELETE FROM project WHERE collection = ?1 AND name = ?2",
+                    params![self.collection, name],
+                )
+                .map_err(|error| map_err(error.to_string()))?;
+        }
+        for project in &projects {
+            let data =
+                serde_json::to_string(project).map_err(|error| map_err(error.to_string()))?;
+            transaction
+                .execute(
+                    "INSERT INTO project (collection, name, data) VALUES (?1, ?2, ?3)
+                    ON CONFLICT (collection, name) DO UPDATE SET data = excluded.data
+                    WHERE data != excluded.data",
+                    params![self.collection, project.name, data],
+                )
+                .map_err(|error| map_err(error.to_string()))?;
+        }
+        transaction
+            .commit()
+            .map_err(|error| map_err(error.to_string()))
+    }
+}
+
+pub fn is_database(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("sqlite" | "sqlite3" | "db")
+    )
+}
+
+/// Picks a store by the file's extension. Anything unrecognized is read as TOML, as projects
+/// files always were before. `collection` picks the collection's rows out of a database, and
+/// isn't needed by single-collection files.
+pub fn open(path: &Path, collection: &str) -> Box<dyn ProjectStore> {
+    let path = path.to_owned();
+    if is_database(&path) {
+        return Box::new(SqliteStore {
+            path,
+            collection: collection.to_owned(),
+        });
+    }
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("json") => Box::new(JsonStore { path }),
+        _ => Box::new(TomlStore { path }),
+    }
+}
+
+/// Copies every project of `collection` from one store to a new one, which can use a different
+/// format.
+pub fn migrate(from: &Path, to: &Path, collection: &str) -> Result<usize, String> {
+    if to.exists() {
+        return Err(format!("{} already exists", to.display()));
+    }
+    let projects = open(from, collection).load()?;
+    let count = projects.len();
+    open(to, collection).save(projects)?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project(name: &str, status: &str) -> ProjectInfo {
+        toml::from_str(&format!(
+            "name = {:?}\nproject_type = \"rust\"\nstatus = {:?}\nlast_opened = 0\npath = \"/src/{}\"",
+            name, status, name
+        ))
+        .unwrap()
+    }
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("projlist-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn statuses(projects: &[ProjectInfo]) -> Vec<(&str, &str)> {
+        projects
+            .iter()
+            .map(|p| (p.name.as_str(), p.status.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn sqlite_updates_changed_rows() {
+        let dir = test_dir("sqlite");
+        let path = dir.join("projects.sqlite");
+        let store = SqliteStore {
+            path: path.clone(),
+            collection: String::from("work"),
+        };
+        let other = SqliteStore {
+            path: path.clone(),
+            collection: String::from("home"),
+        };
+        store
+            .save(vec![project("a", "active"), project("b", "active")])
+            .unwrap();
+        other.save(vec![project("a", "done")]).unwrap();
+        // Records which rows are rewritten from here on
+        Connection::open(&path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE updated (name TEXT);
+                CREATE TRIGGER record_update AFTER UPDATE ON project
+                BEGIN INSERT INTO updated VALUES (new.name); END",
+            )
+            .unwrap();
+        store
+            .save(vec![
+                project("a", "active"),
+                project("b", "done"),
+                project("c", "active"),
+            ])
+            .unwrap();
+        let updated: Vec<String> = Connection::open(&path)
+            .unwrap()
+            .prepare("SELECT name FROM updated")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(updated, ["b"]);
+        assert_eq!(
+            statuses(&store.load().unwrap()),
+            [("a", "active"), ("b", "done"), ("c", "active")]
+        );
+        store.save(vec![project("c", "active")]).unwrap();
+        assert_eq!(statuses(&store.load().unwrap()), [("c", "active")]);
+        // Other collections in the same database are left alone
+        assert_eq!(statuses(&other.load().unwrap()), [("a", "done")]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn migrate_between_stores() {
+        let dir = test_dir("migrate");
+        let toml = dir.join("projects.toml");
+        let json = dir.join("projects.json");
+        let sqlite = dir.join("projects.sqlite");
+        let projects = || vec![project("a", "active"), project("b", "done")];
+        open(&toml, "work").save(projects()).unwrap();
+        assert_eq!(migrate(&toml, &json, "work"), Ok(2));
+        assert_eq!(migrate(&json, &sqlite, "work"), Ok(2));
+        assert!(open(&sqlite, "work").load().unwrap() == projects());
+        // Migrating never overwrites an existing store
+        assert!(migrate(&toml, &json, "work").is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
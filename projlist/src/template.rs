@@ -0,0 +1,235 @@
+use std::{cell::RefCell, path::Path, process::Command, rc::Rc};
+
+use gtk::{gio, glib, prelude::*};
+
+use crate::{add_project, config::Template, open_project, show_error, ProjectProvider};
+
+/// Replaces the variables templates can use in file names, file contents and post-create
+/// commands.
+fn substitute(text: &str, name: &str, project_type: &str) -> String {
+    text.replace("{{name}}", name)
+        .replace("{{type}}", project_type)
+}
+
+/// A project name that can be used as a folder name.
+fn validate_name(name: &str) -> Result<&str, String> {
+    let name = name.trim();
+    if name.is_empty() || name == "." || name == ".." {
+        return Err(String::from("Enter a name"));
+    }
+    if name.contains('/') {
+        return Err(String::from("Names can't contain \"/\""));
+    }
+    Ok(name)
+}
+
+/// Copies everything in the folder `from` into the existing folder `to`.
+fn copy_template(from: &Path, to: &Path, name: &str, project_type: &str) -> Result<(), String> {
+    let map_err_read = |error| format!("Error reading {}: {}", from.display(), error);
+    let map_err_write = |error| format!("Error writing {}: {}", to.display(), error);
+    for entry in std::fs::read_dir(from).map_err(map_err_read)? {
+        let entry = entry.map_err(map_err_read)?;
+        let source = entry.path();
+        let target = to.join(substitute(
+            &entry.file_name().to_string_lossy(),
+            name,
+            project_type,
+        ));
+        if entry.file_type().map_err(map_err_read)?.is_dir() {
+            std::fs::create_dir(&target)
+                .map_err(|error| format!("Error writing {}: {}", target.display(), error))?;
+            copy_template(&source, &target, name, project_type)?;
+            continue;
+        }
+        // Copying first keeps permissions, such as executable scripts
+        std::fs::copy(&source, &target).map_err(map_err_write)?;
+        if let Ok(contents) = std::fs::read_to_string(&source) {
+            if contents.contains("{{") {
+                std::fs::write(&target, substitute(&contents, name, project_type))
+                    .map_err(map_err_write)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run_post_create(command: &str, path: &Path) -> Result<(), String> {
+    let argv = glib::shell_parse_argv(command)
+        .map_err(|error| format!("Invalid command \"{}\": {}", command, error))?;
+    let (program, args) = argv
+        .split_first()
+        .ok_or_else(|| format!("Invalid command \"{}\"", command))?;
+    let output = Command::new(program)
+        .args(args)
+        .current_dir(path)
+        .output()
+        .map_err(|error| format!("Error running \"{}\": {}", command, error))?;
+    if !output.status.success() {
+        return Err(format!(
+            "\"{}\" failed: {}",
+            command,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Copies `template` to `path` and runs its post-create commands there. The folder is removed
+/// again if that fails.
+fn create(template: &Template, path: &Path, name: &str, project_type: &str) -> Result<(), String> {
+    std::fs::create_dir(path).map_err(|error| match error.kind() {
+        std::io::ErrorKind::AlreadyExists => format!("{} already exists", path.display()),
+        _ => format!("Error writing {}: {}", path.display(), error),
+    })?;
+    let result = copy_template(&template.path, path, name, project_type).and_then(|()| {
+        template
+            .post_create
+            .iter()
+            .try_for_each(|command| run_post_create(&substitute(command, name, project_type), path))
+    });
+    if result.is_err() {
+        let _ = std::fs::remove_dir_all(path);
+    }
+    result
+}
+
+fn form_label(text: &str) -> gtk::Label {
+    gtk::Label::builder().label(text).xalign(1.0).build()
+}
+
+pub fn show_dialog(app_window: &gtk::ApplicationWindow, provider: &ProjectProvider) {
+    let types: Vec<String> = provider
+        .app_config
+        .borrow()
+        .template_type_names()
+        .into_iter()
+        .map(String::from)
+        .collect();
+    if types.is_empty() {
+        show_error(
+            app_window,
+            "No templates",
+            "Set a template folder for a project type in type.toml to create projects from it.",
+        );
+        return;
+    }
+    let types: Vec<&str> = types.iter().map(String::as_str).collect();
+
+    let grid = gtk::Grid::builder()
+        .column_spacing(12)
+        .row_spacing(6)
+        .margin_top(12)
+        .margin_bottom(12)
+        .margin_start(12)
+        .margin_end(12)
+        .build();
+    let name = gtk::Entry::builder().hexpand(true).build();
+    grid.attach(&form_label("Name"), 0, 0, 1, 1);
+    grid.attach(&name, 1, 0, 1, 1);
+    let project_type = gtk::DropDown::from_strings(&types);
+    grid.attach(&form_label("Type"), 0, 1, 1, 1);
+    grid.attach(&project_type, 1, 1, 1, 1);
+    let location = Rc::new(RefCell::new(glib::home_dir()));
+    let location_button = gtk::Button::with_label(&location.borrow().to_string_lossy());
+    grid.attach(&form_label("Location"), 0, 2, 1, 1);
+    grid.attach(&location_button, 1, 2, 1, 1);
+
+    let cancel = gtk::Button::with_label("Cancel");
+    let create_button = gtk::Button::builder()
+        .label("Create")
+        .css_classes(["suggested-action"])
+        .build();
+    let header = gtk::HeaderBar::builder().show_title_buttons(false).build();
+    header.pack_start(&cancel);
+    header.pack_end(&create_button);
+    let window = gtk::Window::builder()
+        .title("New from Template")
+        .transient_for(app_window)
+        .modal(true)
+        .default_width(400)
+        .titlebar(&header)
+        .child(&grid)
+        .build();
+
+    cancel.connect_clicked(glib::clone!(@weak window => move |_| window.close()));
+    location_button.connect_clicked(
+        glib::clone!(@weak window, @strong location => move |button| {
+            let dialog = gtk::FileDialog::builder()
+                .title("Location")
+                .modal(true)
+                .initial_folder(&gio::File::for_path(&*location.borrow()))
+                .build();
+            dialog.select_folder(
+                Some(&window),
+                gio::Cancellable::NONE,
+                glib::clone!(@strong location, @weak button => move |result| {
+                    if let Some(path) = result.ok().and_then(|folder| folder.path()) {
+                        button.set_label(&path.to_string_lossy());
+                        location.replace(path);
+                    }
+                }),
+            );
+        }),
+    );
+    create_button.connect_clicked(glib::clone!(
+        @weak window,
+        @weak app_window,
+        @weak name,
+        @weak project_type,
+        @strong provider,
+        @strong location => move |create_button| {
+            let project_name = match validate_name(&name.text()) {
+                Ok(project_name) => project_name.to_owned(),
+                Err(error) => {
+                    name.grab_focus();
+                    show_error(&window, "Invalid name", &error);
+                    return;
+                }
+            };
+            let type_name = project_type
+                .selected_item()
+                .and_downcast::<gtk::StringObject>()
+                .map(|s| String::from(s.string()))
+                .unwrap_or_default();
+            let template = match provider.app_config.borrow().template(&type_name) {
+                Some(template) => template,
+                None => return,
+            };
+            let path = location.borrow().join(&project_name);
+            create_button.set_sensitive(false);
+
+            // Post-create commands can take a while, so run them off the main thread
+            let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+            std::thread::spawn(glib::clone!(@strong path => move || {
+                let result = create(&template, &path, &project_name, &type_name);
+                let _ = sender.send((result, type_name));
+            }));
+            receiver.attach(
+                None,
+                glib::clone!(@weak window, @weak app_window, @weak create_button, @strong provider => @default-return glib::Continue(false), move |(result, type_name)| {
+                    let position = result.and_then(|_| add_project(&provider, &path, Some(&type_name)));
+                    match position {
+                        Ok(position) => {
+                            window.close();
+                            gio::prelude::ActionGroupExt::activate_action(
+                                &app_window,
+                                "select-project",
+                                Some(&position.to_variant()),
+                            );
+                            let item = provider.model.item(position).and_downcast().unwrap();
+                            if let Err(error) = open_project(&provider, &item) {
+                                show_error(&app_window, "Error opening project", &error);
+                            }
+                        }
+                        Err(error) => {
+                            create_button.set_sensitive(true);
+                            show_error(&window, "Error creating project", &error);
+                        }
+                    }
+                    glib::Continue(false)
+                }),
+            );
+        }
+    ));
+    window.present();
+}
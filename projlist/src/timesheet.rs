@@ -0,0 +1,149 @@
+use std::path::Path;
+
+use gtk::{gio, glib, prelude::*};
+use gtk_list_provider::ListProvider;
+
+use crate::{project_info::ProjectInfo, show_error, ProjectProvider};
+
+/// Sessions that started within this many days count as recent.
+pub const RECENT_DAYS: i64 = 7;
+
+fn now() -> i64 {
+    glib::DateTime::now_utc().unwrap().to_unix()
+}
+
+pub fn format_duration(seconds: i64) -> String {
+    let minutes = seconds.max(0) / 60;
+    match minutes / 60 {
+        0 => format!("{}m", minutes),
+        hours => format!("{}h {:02}m", hours, minutes % 60),
+    }
+}
+
+pub fn format_time(time: i64) -> String {
+    glib::DateTime::from_unix_local(time)
+        .and_then(|dt| dt.format("%a %e %b %H:%M"))
+        .map(String::from)
+        .unwrap_or_default()
+}
+
+pub fn total_time(item: &ProjectInfo) -> i64 {
+    let now = now();
+    item.sessions().iter().map(|s| s.duration(now)).sum()
+}
+
+pub fn recent_time(item: &ProjectInfo) -> i64 {
+    let now = now();
+    let since = now - RECENT_DAYS * 24 * 60 * 60;
+    item.sessions()
+        .iter()
+        .filter(|s| s.start >= since)
+        .map(|s| s.duration(now))
+        .sum()
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+fn iso8601(time: i64) -> String {
+    glib::DateTime::from_unix_local(time)
+        .and_then(|dt| dt.format_iso8601())
+        .map(String::from)
+        .unwrap_or_default()
+}
+
+/// Writes one row per session. Running sessions have no end and count up to now.
+fn write_csv(path: &Path, items: &[ProjectInfo]) -> Result<(), String> {
+    let now = now();
+    let mut csv = String::from("Collection,Project,Type,Start,End,Minutes\n");
+    for item in items {
+        for session in item.sessions() {
+            let row = [
+                csv_field(&item.collection()),
+                csv_field(&item.name()),
+                csv_field(&item.project_type()),
+                iso8601(session.start),
+                session.end.map(iso8601).unwrap_or_default(),
+                (session.duration(now) / 60).to_string(),
+            ];
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+    }
+    std::fs::write(path, csv)
+        .map_err(|error| format!("Error writing {}: {}", path.display(), error))
+}
+
+/// Asks where to save a timesheet of the projects currently shown.
+pub fn export(app_window: &gtk::ApplicationWindow, provider: &ProjectProvider) {
+    let model = provider.model();
+    let items: Vec<ProjectInfo> = (0..model.n_items())
+        .filter_map(|position| model.item(position).and_downcast::<ProjectInfo>())
+        .collect();
+    let dialog = gtk::FileDialog::builder()
+        .title("Export Timesheet")
+        .modal(true)
+        .initial_name("timesheet.csv")
+        .build();
+    dialog.save(
+        Some(app_window),
+        gio::Cancellable::NONE,
+        glib::clone!(@weak app_window => move |result| {
+            if let Some(path) = result.ok().and_then(|file| file.path()) {
+                if let Err(error) = write_csv(&path, &items) {
+                    show_error(&app_window, "Error exporting timesheet", &error);
+                }
+            }
+        }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{AppConfig, Collection},
+        project_info::ProjectInfoInner,
+    };
+
+    #[test]
+    fn csv_quoting() {
+        let collection = Collection {
+            name: String::from("Work, home"),
+            path: String::from("/tmp/projects.toml"),
+            notes_dir: None,
+        };
+        let info = toml::from_str(
+            r#"
+            name = 'Say "hi"'
+            project_type = "line\r\nbreak"
+            status = "active"
+            last_opened = 0
+            path = "/src/hi"
+            session = [{ start = 0, end = 600 }]
+            "#,
+        )
+        .unwrap();
+        let item = ProjectInfo::new(ProjectInfoInner::from_config(
+            info,
+            &collection,
+            &AppConfig::default(),
+        ));
+        let path = std::env::temp_dir().join(format!("projlist-csv-{}.csv", std::process::id()));
+        write_csv(&path, &[item]).unwrap();
+        let csv = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let (_, row) = csv.split_once('\n').unwrap();
+        assert!(
+            row.starts_with("\"Work, home\",\"Say \"\"hi\"\"\",\"line\r\nbreak\","),
+            "{:?}",
+            row
+        );
+        assert!(row.ends_with(",10\n"), "{:?}", row);
+    }
+}
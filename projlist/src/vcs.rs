@@ -0,0 +1,187 @@
+use std::{path::Path, process::Command};
+
+const RECENT_COMMITS: usize = 5;
+
+#[derive(Clone, Debug)]
+pub struct CommitInfo {
+    pub hash: String,
+    pub author: String,
+    pub time: i64,
+    pub summary: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct VcsStatus {
+    pub branch: String,
+    pub dirty: bool,
+    pub upstream: bool,
+    pub ahead: u32,
+    pub behind: u32,
+    pub recent_commits: Vec<CommitInfo>,
+}
+
+impl VcsStatus {
+    pub fn ahead_behind(&self) -> String {
+        if self.upstream {
+            format!("↑{} ↓{}", self.ahead, self.behind)
+        } else {
+            String::new()
+        }
+    }
+}
+
+fn git(path: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(path)
+        .args(args)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+pub fn read_status(path: &Path) -> Option<VcsStatus> {
+    if !path.join(".git").exists() {
+        return None;
+    }
+
+    let output = git(path, &["status", "--porcelain=v2", "--branch"])?;
+    let mut status = parse_status(&output);
+
+    // Fields are separated with the ASCII unit separator so commit subjects can't break parsing.
+    let output = git(
+        path,
+        &[
+            "log",
+            &format!("--max-count={}", RECENT_COMMITS),
+            "--format=%h%x1f%an%x1f%at%x1f%s",
+        ],
+    )
+    .unwrap_or_default();
+    status.recent_commits = parse_log(&output);
+    Some(status)
+}
+
+/// Reads the output of `git status --porcelain=v2 --branch`, which prints branch headers
+/// prefixed with `#`, followed by one line per changed or untracked file.
+fn parse_status(output: &str) -> VcsStatus {
+    let mut status = VcsStatus::default();
+    for line in output.lines() {
+        if let Some(header) = line.strip_prefix("# ") {
+            if let Some(head) = header.strip_prefix("branch.head ") {
+                status.branch = head.to_owned();
+            } else if let Some(ab) = header.strip_prefix("branch.ab ") {
+                let mut counts = ab.split_whitespace();
+                status.upstream = true;
+                status.ahead = counts
+                    .next()
+                    .and_then(|s| s.trim_start_matches('+').parse().ok())
+                    .unwrap_or(0);
+                status.behind = counts
+                    .next()
+                    .and_then(|s| s.trim_start_matches('-').parse().ok())
+                    .unwrap_or(0);
+            }
+        } else if !line.is_empty() {
+            status.dirty = true;
+        }
+    }
+    status
+}
+
+fn parse_log(output: &str) -> Vec<CommitInfo> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.splitn(4, '\x1f').collect();
+            match fields[..] {
+                [hash, author, time, summary] => Some(CommitInfo {
+                    hash: hash.to_owned(),
+                    author: author.to_owned(),
+                    time: time.parse().unwrap_or(0),
+                    summary: summary.to_owned(),
+                }),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn branch_with_upstream() {
+        let status = parse_status(
+            "# branch.oid 1234567890abcdef1234567890abcdef12345678
+# branch.head main
+# branch.upstream origin/main
+# branch.ab +2 -1
+",
+        );
+        assert_eq!(status.branch, "main");
+        assert!(!status.dirty);
+        assert!(status.upstream);
+        assert_eq!((status.ahead, status.behind), (2, 1));
+        assert_eq!(status.ahead_behind(), "↑2 ↓1");
+    }
+
+    #[test]
+    fn no_upstream() {
+        let status = parse_status(
+            "# branch.oid 1234567890abcdef1234567890abcdef12345678
+# branch.head feature
+? notes.txt
+",
+        );
+        assert_eq!(status.branch, "feature");
+        assert!(status.dirty);
+        assert!(!status.upstream);
+        assert_eq!(status.ahead_behind(), "");
+    }
+
+    #[test]
+    fn detached_head() {
+        let status = parse_status(
+            "# branch.oid 1234567890abcdef1234567890abcdef12345678
+# branch.head (detached)
+1 .M N... 100644 100644 100644 abc123 abc123 src/main.rs
+",
+        );
+        assert_eq!(status.branch, "(detached)");
+        assert!(status.dirty);
+        assert!(!status.upstream);
+    }
+
+    #[test]
+    fn empty_repository() {
+        let status = parse_status(
+            "# branch.oid (initial)
+# branch.head main
+",
+        );
+        assert_eq!(status.branch, "main");
+        assert!(!status.dirty);
+        assert!(parse_log("").is_empty());
+    }
+
+    #[test]
+    fn log_lines() {
+        let commits = parse_log(
+            "abc1234\x1fAda\x1f1700000000\x1fFix: keep \x1f in subjects
+def5678\x1fGrace\x1fnot a time\x1fAdd tests
+incomplete line
+",
+        );
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].hash, "abc1234");
+        assert_eq!(commits[0].author, "Ada");
+        assert_eq!(commits[0].time, 1700000000);
+        assert_eq!(commits[0].summary, "Fix: keep \x1f in subjects");
+        assert_eq!(commits[1].time, 0);
+    }
+}